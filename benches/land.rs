@@ -0,0 +1,39 @@
+//! Lookups on `Land`, which streams its points in seeded chunks kept in an LRU
+//! cache.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use lander::{
+    geom::Point,
+    terrain::{generator::MidpointDisplacement, land::Land, Terrain},
+};
+
+fn long_flight(c: &mut Criterion) {
+    c.bench_function("land get over long flight", |b| {
+        b.iter(|| {
+            let mut land = Land::with_generator(Box::new(MidpointDisplacement::new(1)));
+            for step in 0..20_000 {
+                let x = (step as f32) * 0.5;
+                black_box(land.get(Point(x, 0.0)));
+            }
+        })
+    });
+
+    c.bench_function("land get within window", |b| {
+        let mut land = Land::with_generator(Box::new(MidpointDisplacement::new(1)));
+        land.get(Point(0.0, 0.0));
+        b.iter(|| {
+            for step in -400..400 {
+                black_box(land.get(Point(step as f32, 0.0)));
+            }
+        })
+    });
+
+    c.bench_function("land segments in view", |b| {
+        let mut land = Land::with_generator(Box::new(MidpointDisplacement::new(1)));
+        land.get(Point(0.0, 0.0));
+        b.iter(|| black_box(land.segments(-200.0, 200.0)))
+    });
+}
+
+criterion_group!(benches, long_flight);
+criterion_main!(benches);
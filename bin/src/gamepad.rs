@@ -0,0 +1,71 @@
+use gilrs::{EventType, Gilrs};
+use lander::input::{Axis, Button, Event};
+
+/// Gamepads plugged into the machine, if the platform lets us read them.
+pub struct Gamepads(Option<Gilrs>);
+impl Gamepads {
+    pub fn new() -> Gamepads {
+        let gilrs = Gilrs::new()
+            .map_err(|err| println!("Gamepads unavailable: {}", err))
+            .ok();
+        Gamepads(gilrs)
+    }
+
+    pub fn poll(&mut self) -> Option<Event> {
+        let gilrs = self.0.as_mut()?;
+        while let Some(event) = gilrs.next_event() {
+            let pad: usize = event.id.into();
+            let pad = pad as u32;
+            let event = match event.event {
+                EventType::ButtonChanged(button, value, _) => {
+                    button_of(button).map(|button| Event::Button { pad, button, value })
+                }
+                EventType::AxisChanged(axis, value, _) => {
+                    axis_of(axis).map(|axis| Event::Axis { pad, axis, value })
+                }
+                _ => None,
+            };
+            if event.is_some() {
+                return event;
+            }
+        }
+        None
+    }
+}
+
+fn button_of(button: gilrs::Button) -> Option<Button> {
+    use gilrs::Button as B;
+
+    Some(match button {
+        B::South => Button::South,
+        B::East => Button::East,
+        B::West => Button::West,
+        B::North => Button::North,
+        B::LeftTrigger => Button::LeftTrigger,
+        B::RightTrigger => Button::RightTrigger,
+        B::LeftTrigger2 => Button::LeftTrigger2,
+        B::RightTrigger2 => Button::RightTrigger2,
+        B::Select => Button::Select,
+        B::Start => Button::Start,
+        B::LeftThumb => Button::LeftThumb,
+        B::RightThumb => Button::RightThumb,
+        B::DPadUp => Button::DPadUp,
+        B::DPadDown => Button::DPadDown,
+        B::DPadLeft => Button::DPadLeft,
+        B::DPadRight => Button::DPadRight,
+        B::Mode => Button::Mode,
+        _ => return None,
+    })
+}
+
+fn axis_of(axis: gilrs::Axis) -> Option<Axis> {
+    use gilrs::Axis as A;
+
+    Some(match axis {
+        A::LeftStickX => Axis::LeftStickX,
+        A::LeftStickY => Axis::LeftStickY,
+        A::RightStickX => Axis::RightStickX,
+        A::RightStickY => Axis::RightStickY,
+        _ => return None,
+    })
+}
@@ -1,6 +1,19 @@
-use std::time::{Duration, Instant};
+mod gamepad;
+mod network;
 
-use lander::{ship::Throttle, world::World};
+use std::time::{Duration, Instant, SystemTime};
+
+use gamepad::Gamepads;
+use lander::{
+    game::{Mission, State},
+    highscore::{Entry, FileStorage, HighScores, Storage},
+    input::{self, InputMap},
+    render::wgpu::target::WgpuRenderTarget,
+    ship::{definition::ShipDefinition, ThrusterModel},
+    terrain::cave::Cave,
+    world::World,
+};
+use network::Connection;
 use winit::{
     dpi::PhysicalSize,
     event::{DeviceEvent, Event, KeyboardInput, WindowEvent},
@@ -20,7 +33,66 @@ fn main() {
         .build(&event_loop)
         .unwrap();
 
-    let mut world = World::from(&window);
+    let args: Vec<String> = std::env::args().collect();
+    let server = args
+        .iter()
+        .position(|arg| arg == "--connect")
+        .and_then(|index| args.get(index + 1));
+    let (mut world, mut connection) = match server {
+        Some(url) => {
+            let (connection, lockstep, game) =
+                Connection::open(url).expect("Could not join the server");
+            let target = WgpuRenderTarget::new(&window);
+            (
+                World::with_lockstep(target, lockstep, game),
+                Some(connection),
+            )
+        }
+        None if args.iter().any(|arg| arg == "--cave") => {
+            let seed = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |since| since.as_nanos() as u64);
+            let target = WgpuRenderTarget::new(&window);
+            let cave = Box::new(Cave::level(seed));
+            (World::with_terrain(target, cave), None)
+        }
+        None => (World::from(&window), None),
+    };
+    let data_dir = dirs::data_dir()
+        .unwrap_or_else(|| ".".into())
+        .join("lander");
+    let mut high_scores = HighScores::load(FileStorage::new(data_dir.clone()), &world.game().rules);
+    if connection.is_none() {
+        high_scores.load_ghosts(world.game());
+    }
+
+    // Write out the default bindings on first run so they can be edited.
+    let mut bindings = FileStorage::new(data_dir);
+    *world.input_map() = InputMap::load(&bindings);
+    if bindings.read("lander-bindings").is_none() {
+        world.input_map().save(&mut bindings).ok();
+    }
+    if connection.is_none() && args.iter().any(|arg| arg == "--two-players") {
+        let ship = world.add_ship();
+        world.add_view(ship);
+    }
+    if connection.is_none() && args.iter().any(|arg| arg == "--hard") {
+        world.game().set_thrusters(ThrusterModel::hard());
+    }
+    let craft = args
+        .iter()
+        .position(|arg| arg == "--craft")
+        .and_then(|index| args.get(index + 1));
+    if let (None, Some(craft)) = (&connection, craft) {
+        world
+            .game()
+            .set_craft(load_craft(craft).expect("Could not load the craft"));
+    }
+    if connection.is_none() && args.iter().any(|arg| arg == "--delivery") {
+        world.game().set_mission(Mission::Delivery);
+    }
+
+    let mut gamepads = Gamepads::new();
 
     event_loop.run(move |event, _loop_target, control_flow| {
         *control_flow = ControlFlow::WaitUntil(Instant::now() + Duration::from_millis(33));
@@ -32,7 +104,56 @@ fn main() {
                 world.resize(size.width, size.height);
             }
             Event::RedrawRequested(_) => {
+                while let Some(event) = gamepads.poll() {
+                    world.input(&event);
+                }
+                if let Some(link) = &mut connection {
+                    if let Err(err) = link.exchange(&mut world) {
+                        println!("Lost the server: {}", err);
+                        *control_flow = ControlFlow::Exit;
+                        return;
+                    }
+                    if let Some(tick) = world.desync() {
+                        window.set_title(&format!("Lander - Out of sync since tick {}", tick));
+                    }
+                }
                 world.redraw();
+                while let Some(state) = world.poll_event() {
+                    let game = world.game();
+                    let score = game.run().total();
+                    window.set_title(&format!("Lander - {} - Score {}", describe(state), score));
+                    if state != State::GameOver || connection.is_some() {
+                        continue;
+                    }
+
+                    let date = SystemTime::now()
+                        .duration_since(SystemTime::UNIX_EPOCH)
+                        .map(|since| since.as_secs())
+                        .unwrap_or(0);
+                    let rank = Entry::from_game(game, date)
+                        .and_then(|entry| high_scores.submit(entry, &game.rules));
+                    if let Some(rank) = rank {
+                        window.set_title(&format!(
+                            "Lander - New high score #{}: {} - Press an arrow key to restart",
+                            rank + 1,
+                            score
+                        ));
+                    }
+                }
+                // Like the web status line, the readout follows the ship every
+                // frame while it flies.
+                if world.state() == State::Flying && world.desync().is_none() {
+                    let hull = world
+                        .telemetry()
+                        .and_then(|telemetry| telemetry.health.values().copied().reduce(f32::min))
+                        .unwrap_or(1.0);
+                    let score = world.game().run().total();
+                    window.set_title(&format!(
+                        "Lander - Flying - Score {} - Hull {:.0}%",
+                        score,
+                        hull * 100.0
+                    ));
+                }
             }
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested,
@@ -40,6 +161,16 @@ fn main() {
             } => {
                 *control_flow = ControlFlow::Exit;
             }
+            Event::WindowEvent {
+                event: WindowEvent::Focused(focused),
+                ..
+            } => {
+                if focused {
+                    world.resume();
+                } else {
+                    world.pause();
+                }
+            }
             Event::DeviceEvent {
                 event:
                     DeviceEvent::Key(KeyboardInput {
@@ -49,19 +180,12 @@ fn main() {
                     }),
                 ..
             } => {
-                let activate = match state {
+                let pressed = match state {
                     winit::event::ElementState::Pressed => true,
                     winit::event::ElementState::Released => false,
                 };
-                let throttle = match keycode {
-                    winit::event::VirtualKeyCode::Left => Some(Throttle::Left),
-                    winit::event::VirtualKeyCode::Up => Some(Throttle::Bottom),
-                    winit::event::VirtualKeyCode::Right => Some(Throttle::Right),
-                    _ => None,
-                };
-                if let Some(throttle) = throttle {
-                    world.control(throttle, activate);
-                }
+                let code = code(keycode);
+                world.input(&input::Event::Key { code, pressed });
             }
             Event::NewEvents(_) => {
                 window.request_redraw();
@@ -70,3 +194,41 @@ fn main() {
         }
     });
 }
+
+/// Names keys after the web `KeyboardEvent.code` the bindings use.
+fn code(keycode: winit::event::VirtualKeyCode) -> String {
+    use winit::event::VirtualKeyCode;
+
+    let name = format!("{:?}", keycode);
+    match keycode {
+        VirtualKeyCode::Left
+        | VirtualKeyCode::Up
+        | VirtualKeyCode::Right
+        | VirtualKeyCode::Down => format!("Arrow{}", name),
+        _ if name.len() == 1 => format!("Key{}", name),
+        _ if name.len() == 4 && name.starts_with("Key") => name.replace("Key", "Digit"),
+        _ => name,
+    }
+}
+
+fn describe(state: State) -> &'static str {
+    match state {
+        State::Title => "Press an arrow key to start",
+        State::Countdown => "Get ready",
+        State::Flying => "Flying",
+        State::Landed => "Landed! Press an arrow key to take off",
+        State::Crashed => "Crashed! Press an arrow key to continue",
+        State::GameOver => "Game over. Press an arrow key to restart",
+    }
+}
+
+/// A built in craft by name, or a definition read from a JSON file.
+fn load_craft(craft: &str) -> Result<ShipDefinition, String> {
+    match ShipDefinition::named(craft) {
+        Some(craft) => Ok(craft),
+        None => {
+            let json = std::fs::read_to_string(craft).map_err(|e| e.to_string())?;
+            ShipDefinition::from_json(&json)
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use std::{error::Error, io, net::TcpStream};
+
+use lander::{
+    game::Game,
+    net::{Lockstep, Message},
+    render::wgpu::target::WgpuRenderTarget,
+    world::World,
+};
+use tungstenite::WebSocket;
+
+/// WebSocket link to a `lander-server` relay.
+pub struct Connection(WebSocket<TcpStream>);
+impl Connection {
+    /// Connects and waits for the relay to hand over the session so far.
+    pub fn open(url: &str) -> Result<(Connection, Lockstep, Game), Box<dyn Error>> {
+        let (mut socket, _) = tungstenite::connect(url)?;
+        let (lockstep, game) = loop {
+            if let tungstenite::Message::Text(text) = socket.read_message()? {
+                if let Some(joined) = Message::decode(&text).and_then(Lockstep::join) {
+                    break joined;
+                }
+            }
+        };
+        socket.get_mut().set_nonblocking(true)?;
+        Ok((Connection(socket), lockstep, game))
+    }
+
+    /// Hands everything the relay sent to the world and sends back what the
+    /// world has for it, without blocking.
+    pub fn exchange(&mut self, world: &mut World<WgpuRenderTarget>) -> Result<(), Box<dyn Error>> {
+        loop {
+            match self.0.read_message() {
+                Ok(tungstenite::Message::Text(text)) => {
+                    if let Some(message) = Message::decode(&text) {
+                        world.receive(message);
+                    }
+                }
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(err)) if err.kind() == io::ErrorKind::WouldBlock => {
+                    break
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        while let Some(message) = world.poll_outgoing() {
+            match self
+                .0
+                .write_message(tungstenite::Message::Text(message.encode()))
+            {
+                Err(tungstenite::Error::Io(err)) if err.kind() == io::ErrorKind::WouldBlock => {}
+                result => result?,
+            }
+        }
+        match self.0.write_pending() {
+            Err(tungstenite::Error::Io(err)) if err.kind() == io::ErrorKind::WouldBlock => Ok(()),
+            result => Ok(result?),
+        }
+    }
+}
@@ -1,65 +1,102 @@
-import {Throttle} from 'lander';
+import {State} from 'lander';
 
 async function main() {
     const lander = await import('lander');
     const canvas = document.getElementById('canvas') as HTMLCanvasElement;
-    const world = new lander.World(canvas);
+    const status = document.getElementById('status') as HTMLDivElement;
+    const world = new URLSearchParams(location.search).has('cave')
+        ? lander.World.cave(canvas, Math.floor(Math.random() * 0xffffffff))
+        : new lander.World(canvas);
     console.log("world ready");
+
+    function describe(state: State): string {
+        switch (state) {
+            case lander.State.Title: return "Press an arrow key to start";
+            case lander.State.Countdown: return "Get ready";
+            case lander.State.Flying: return "";
+            case lander.State.Landed: return `Landed! +${world.last_score()} (score ${world.score()}). Press an arrow key to take off`;
+            case lander.State.Crashed: return "Crashed! Press an arrow key to continue";
+            case lander.State.GameOver:
+                const best = Array.from(world.high_scores()).map((score, rank) => `${rank + 1}. ${score}`);
+                return `Game over. Score ${world.score()}. High scores: ${best.join(' ')}. Press an arrow key to restart`;
+        }
+    }
+
+    function readout(): string {
+        const json = world.telemetry();
+        if (json === undefined) {
+            return "";
+        }
+        const telemetry = JSON.parse(json);
+        const worn = Object.entries(telemetry.health as Record<string, number>)
+            .filter(([, health]) => health < 1)
+            .map(([part, health]) => `${part} ${Math.round(health * 100)}%`);
+        return `Fuel ${telemetry.fuel.toFixed(1)}s, speed ${telemetry.speed.toFixed(1)} ${worn.join(' ')}`;
+    }
+
     function render() {
         canvas.width = window.innerWidth;
         canvas.height = window.innerHeight;
 
+        pollGamepads();
         world.redraw();
+        let state;
+        while ((state = world.poll_event()) !== undefined) {
+            status.textContent = describe(state);
+        }
+        if (world.state() == lander.State.Flying) {
+            status.textContent = readout();
+        }
         requestAnimationFrame(render)
     }
     requestAnimationFrame(render);
 
-    function getThrottle(ev: KeyboardEvent | TouchEvent, cb: (t: Throttle) => void) {
-        if (ev instanceof KeyboardEvent) {
-            if (ev.key == 'ArrowUp') {
-                cb(lander.Throttle.Bottom);
-            } else if (ev.key == 'ArrowLeft') {
-                cb(lander.Throttle.Left);
-            } else if (ev.key == 'ArrowRight') {
-                cb(lander.Throttle.Right);
-            }
-        } else if (ev instanceof TouchEvent) {
-            for (const touch of ev.changedTouches) {
-                const x = touch.pageX / window.innerWidth;
-                const y = touch.pageY / window.innerHeight;
-                if (y < 0.3) {
-                    cb(lander.Throttle.Bottom);
-                } else {
-                    if (x < 0.5) {
-                        cb(lander.Throttle.Left);
-                    } else {
-                        cb(lander.Throttle.Right);
-                    }
-                }
-            }
+    function touch(ev: TouchEvent, down: boolean) {
+        for (const touch of ev.changedTouches) {
+            world.touch(touch.identifier, touch.pageX / window.innerWidth, touch.pageY / window.innerHeight, down);
         }
     }
 
-    function control(ev: KeyboardEvent | TouchEvent, down: boolean) {
-        getThrottle(ev, (key) => {
-            world.control(key, down);
-        });
-    }
-
     canvas.addEventListener('touchend', (ev) => {
-        control(ev, false);
+        touch(ev, false);
+    })
+
+    canvas.addEventListener('touchcancel', (ev) => {
+        touch(ev, false);
     })
 
     canvas.addEventListener('touchstart', (ev) => {
-        control(ev, true);
+        touch(ev, true);
+    })
+
+    canvas.addEventListener('touchmove', (ev) => {
+        touch(ev, true);
     })
 
     document.body.addEventListener('keydown', (ev) => {
-        control(ev, true);
+        world.key(ev.code, true);
     })
 
     document.body.addEventListener('keyup', (ev) => {
-        control(ev, false);
+        world.key(ev.code, false);
+    })
+
+    function pollGamepads() {
+        for (const gamepad of navigator.getGamepads()) {
+            if (!gamepad || gamepad.mapping != 'standard') {
+                continue;
+            }
+            gamepad.buttons.forEach((button, index) => world.gamepad_button(gamepad.index, index, button.value));
+            gamepad.axes.forEach((value, index) => world.gamepad_axis(gamepad.index, index, value));
+        }
+    }
+
+    document.addEventListener('visibilitychange', () => {
+        if (document.hidden) {
+            world.pause();
+        } else {
+            world.resume();
+        }
     })
 }
 
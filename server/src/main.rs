@@ -0,0 +1,299 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    error::Error,
+    io::{self, Read, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::SystemTime,
+};
+
+use lander::{
+    game::ShipId,
+    net::{Envelope, Message, Relay},
+};
+use tungstenite::WebSocket;
+
+/// The relay plus a queue of outgoing text for each connected client.
+struct Hub {
+    relay: Relay,
+    outboxes: BTreeMap<ShipId, mpsc::Sender<Traffic>>,
+}
+impl Hub {
+    fn dispatch(&self, out: Vec<Envelope>) {
+        for envelope in out {
+            let text = envelope.message.encode();
+            for (&ship, outbox) in &self.outboxes {
+                if envelope.is_for(ship) {
+                    let _ = outbox.send(Traffic::Queued(text.clone()));
+                }
+            }
+        }
+    }
+}
+
+fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
+        .try_init()
+        .unwrap_or_else(|err| println!("env_logger::init() failed: {}", err));
+
+    let mut args = std::env::args().skip(1);
+    let address = args.next().unwrap_or_else(|| "127.0.0.1:9001".to_string());
+    let seed = args
+        .next()
+        .and_then(|seed| seed.parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|since| since.as_nanos() as u64)
+                .unwrap_or(0)
+        });
+
+    let listener = TcpListener::bind(&address).expect("Could not bind the address");
+    log::info!("Relaying seed {} on ws://{}", seed, address);
+    serve(listener, Relay::new(seed, true));
+}
+
+fn serve(listener: TcpListener, relay: Relay) {
+    let hub = Arc::new(Mutex::new(Hub {
+        relay,
+        outboxes: BTreeMap::new(),
+    }));
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                log::warn!("Could not accept a connection: {}", err);
+                continue;
+            }
+        };
+        let hub = hub.clone();
+        thread::spawn(move || match tungstenite::accept(Pipe::new(stream)) {
+            Ok(socket) => client(socket, &hub),
+            Err(err) => log::warn!("WebSocket handshake failed: {}", err),
+        });
+    }
+}
+
+/// What a client's thread wakes up for.
+enum Traffic {
+    /// Bytes read off the client's stream, empty once it closed.
+    Received(Vec<u8>),
+    /// Text the hub queued for the client.
+    Queued(String),
+}
+
+/// The client's stream as its one WebSocket sees it. Writes go straight out,
+/// while reads after the handshake only see bytes the reader thread already
+/// handed over, so reading never holds up writing.
+struct Pipe {
+    stream: TcpStream,
+    received: VecDeque<u8>,
+    handshaking: bool,
+}
+impl Pipe {
+    fn new(stream: TcpStream) -> Pipe {
+        Pipe {
+            stream,
+            received: VecDeque::new(),
+            handshaking: true,
+        }
+    }
+}
+impl Read for Pipe {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.handshaking {
+            self.stream.read(buf)
+        } else if self.received.is_empty() {
+            Err(io::ErrorKind::WouldBlock.into())
+        } else {
+            self.received.read(buf)
+        }
+    }
+}
+impl Write for Pipe {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+fn client(mut socket: WebSocket<Pipe>, hub: &Mutex<Hub>) {
+    let (sender, traffic) = mpsc::channel();
+    let ship = {
+        let mut hub = hub.lock().unwrap();
+        let (ship, out) = hub.relay.connect();
+        hub.outboxes.insert(ship, sender.clone());
+        hub.dispatch(out);
+        ship
+    };
+    log::info!("{:?} joined", ship);
+
+    socket.get_mut().handshaking = false;
+    let reader = socket
+        .get_ref()
+        .stream
+        .try_clone()
+        .map(|stream| thread::spawn(move || receive(stream, sender)));
+    let result = match &reader {
+        Ok(_) => pump(&mut socket, ship, &traffic, hub),
+        Err(err) => Err(err.to_string().into()),
+    };
+    if let Err(err) = result {
+        log::info!("{:?} dropped: {}", ship, err);
+    }
+
+    {
+        let mut hub = hub.lock().unwrap();
+        hub.outboxes.remove(&ship);
+        let out = hub.relay.disconnect(ship);
+        hub.dispatch(out);
+    }
+    // Shutting the stream down wakes the reader.
+    let _ = socket.get_ref().stream.shutdown(Shutdown::Both);
+    if let Ok(reader) = reader {
+        let _ = reader.join();
+    }
+    log::info!("{:?} left", ship);
+}
+
+/// Drives the client's WebSocket until the client goes away, blocking until
+/// it sends something or the hub queues text for it. Only this thread writes
+/// frames, replies to pings included.
+fn pump(
+    socket: &mut WebSocket<Pipe>,
+    ship: ShipId,
+    traffic: &mpsc::Receiver<Traffic>,
+    hub: &Mutex<Hub>,
+) -> Result<(), Box<dyn Error>> {
+    loop {
+        // The handshake may already have read the first messages.
+        loop {
+            match socket.read_message() {
+                Ok(tungstenite::Message::Text(text)) => {
+                    if let Some(message) = Message::decode(&text) {
+                        let mut hub = hub.lock().unwrap();
+                        let out = hub.relay.receive(ship, message);
+                        hub.dispatch(out);
+                    }
+                }
+                Ok(tungstenite::Message::Close(_)) => return Ok(()),
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(err)) if err.kind() == io::ErrorKind::WouldBlock => {
+                    break
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        match traffic.recv()? {
+            Traffic::Received(bytes) if bytes.is_empty() => return Ok(()),
+            Traffic::Received(bytes) => socket.get_mut().received.extend(bytes),
+            Traffic::Queued(text) => socket.write_message(tungstenite::Message::Text(text))?,
+        }
+    }
+}
+
+/// Hands whatever the client sends to its thread, blocking while it is
+/// quiet, until the client goes away.
+fn receive(mut stream: TcpStream, traffic: mpsc::Sender<Traffic>) {
+    let mut buffer = [0; 4096];
+    loop {
+        let read = match stream.read(&mut buffer) {
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            read => read.unwrap_or(0),
+        };
+        if traffic
+            .send(Traffic::Received(buffer[..read].to_vec()))
+            .is_err()
+            || read == 0
+        {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use lander::{game::Game, net::Lockstep, ship::Throttle};
+    use std::time::Duration;
+
+    struct Client {
+        socket: WebSocket<TcpStream>,
+        lockstep: Lockstep,
+        game: Game,
+    }
+    impl Client {
+        fn join(address: &str) -> Client {
+            let (mut socket, _) = tungstenite::connect(format!("ws://{}", address)).unwrap();
+            let text = socket.read_message().unwrap().into_text().unwrap();
+            let (lockstep, game) = Lockstep::join(Message::decode(&text).unwrap()).unwrap();
+            socket
+                .get_mut()
+                .set_read_timeout(Some(Duration::from_millis(2)))
+                .unwrap();
+            Client {
+                socket,
+                lockstep,
+                game,
+            }
+        }
+
+        fn exchange(&mut self) {
+            while let Some(message) = self.lockstep.poll_outgoing() {
+                let text = tungstenite::Message::Text(message.encode());
+                self.socket.write_message(text).unwrap();
+            }
+            while let Ok(message) = self.socket.read_message() {
+                let message = Message::decode(message.to_text().unwrap()).unwrap();
+                self.lockstep.receive(message);
+            }
+        }
+
+        /// Exchanges messages until the session reaches `tick`.
+        fn run_to(&mut self, tick: u64) {
+            while self.lockstep.tick() < tick {
+                self.exchange();
+                self.lockstep.advance(&mut self.game);
+            }
+        }
+    }
+
+    #[test]
+    fn headless_clients_agree_through_the_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || serve(listener, Relay::new(9, true)));
+
+        let clients: Vec<_> = (0..2)
+            .map(|index| {
+                let address = address.clone();
+                thread::spawn(move || {
+                    let mut client = Client::join(&address);
+                    client.run_to(Game::countdown() + 10 * index);
+                    client.lockstep.control(Throttle::Bottom, 1.0);
+                    client.run_to(Game::countdown() + 100);
+                    client.lockstep.control(Throttle::Bottom, 0.0);
+                    client.run_to(Game::countdown() + 200);
+                    assert_eq!(client.game.pilots().count(), 2);
+                    (
+                        client.lockstep.tick(),
+                        client.game.state_hash(),
+                        client.lockstep.desync(),
+                    )
+                })
+            })
+            .collect();
+        let results: Vec<_> = clients
+            .into_iter()
+            .map(|client| client.join().unwrap())
+            .collect();
+
+        assert_eq!(results[0], results[1]);
+        assert_eq!(results[0].2, None);
+    }
+}
@@ -0,0 +1,190 @@
+use rand::Rng;
+
+use crate::{
+    geom::{Mat3, Point},
+    ship::Ship,
+    terrain::Terrain,
+};
+
+#[derive(Clone, Copy, Debug)]
+pub enum CameraMode {
+    Follow,
+    Fixed { position: Point, zoom: f32 },
+    LookAhead { seconds: f32 },
+    Frame { target: Point },
+}
+
+pub struct Camera {
+    pub mode: CameraMode,
+    pub min_zoom: f32,
+    pub max_zoom: f32,
+    pub zoom_smoothing: f32,
+    pub position_smoothing: f32,
+    pub rotate: bool,
+    position: Point,
+    zoom: f32,
+    rotation: f32,
+    shake: f32,
+    shake_offset: Point,
+}
+impl Default for Camera {
+    fn default() -> Camera {
+        Camera::new()
+    }
+}
+impl Camera {
+    pub fn new() -> Camera {
+        Camera {
+            mode: CameraMode::Follow,
+            min_zoom: 0.001,
+            max_zoom: 0.02,
+            zoom_smoothing: 1.5,
+            position_smoothing: 0.0,
+            rotate: false,
+            position: Point::zero(),
+            zoom: 0.001,
+            rotation: 0.0,
+            shake: 0.0,
+            shake_offset: Point::zero(),
+        }
+    }
+
+    pub fn position(&self) -> Point {
+        self.position
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    pub fn shake(&mut self, amount: f32) {
+        self.shake = self.shake.max(amount);
+    }
+
+    pub fn update(&mut self, dt: f32, ship: &Ship, land: &mut dyn Terrain) {
+        let origin = ship.origin();
+        let (position, zoom) = match self.mode {
+            CameraMode::Follow => (origin, Self::ground_zoom(origin, land)),
+            CameraMode::Fixed { position, zoom } => (position, zoom),
+            CameraMode::LookAhead { seconds } => {
+                let ahead = origin + ship.velocity() * seconds;
+                (ahead, Self::ground_zoom(ahead, land))
+            }
+            CameraMode::Frame { target } => {
+                let center = (origin + target) * 0.5;
+                let extent = (origin - center).0.abs().max((origin - center).1.abs());
+                (center, 1.0 / (extent + 30.0))
+            }
+        };
+        let zoom = zoom.max(self.min_zoom).min(self.max_zoom);
+        let rotation = if self.rotate {
+            let direction = ship.direction();
+            (-direction.0).atan2(direction.1)
+        } else {
+            0.0
+        };
+
+        let position_factor = Self::smoothing(dt, self.position_smoothing);
+        self.position = self.position + (position - self.position) * position_factor;
+        self.zoom += (zoom - self.zoom) * Self::smoothing(dt, self.zoom_smoothing);
+        self.rotation += Self::wrap(rotation - self.rotation) * position_factor;
+
+        self.shake *= (-dt * 6.0).exp();
+        let mut rng = rand::thread_rng();
+        self.shake_offset = Point(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)) * self.shake;
+    }
+
+    pub fn transform(&self, window: (u32, u32)) -> Mat3 {
+        let position = self.position + self.shake_offset;
+        let aspect = (window.1 as f32) / (window.0 as f32);
+
+        Mat3::scale(aspect, 1.0)
+            * Mat3::scale(self.zoom, self.zoom)
+            * Mat3::rotate(-self.rotation)
+            * Mat3::translate(-position.0, -position.1)
+    }
+
+    pub fn view_radius(&self, window: (u32, u32)) -> f32 {
+        let aspect = (window.1 as f32) / (window.0 as f32);
+        let half_width = 1.0 / (self.zoom * aspect);
+        let half_height = 1.0 / self.zoom;
+        Point(half_width, half_height).len() + self.shake
+    }
+
+    fn ground_zoom(position: Point, land: &mut dyn Terrain) -> f32 {
+        let ground = land.get(position);
+        let ground = ground.0 + ground.direction() * (position - ground.0).0;
+        let distance = (position - ground).len() + 30.0;
+        1.0 / distance
+    }
+
+    fn smoothing(dt: f32, time_constant: f32) -> f32 {
+        if time_constant <= 0.0 {
+            return 1.0;
+        }
+        1.0 - (-dt / time_constant).exp()
+    }
+
+    fn wrap(angle: f32) -> f32 {
+        let pi = std::f32::consts::PI;
+        (angle + pi).rem_euclid(2.0 * pi) - pi
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::terrain::polyline::Polyline;
+
+    fn update(camera: &mut Camera, dt: f32, frames: u32) {
+        let ship = Ship::spawn(Point(0.0, 0.0));
+        let mut land = Polyline::new(vec![Point(-1000.0, 0.0), Point(1000.0, 0.0)]);
+        (0..frames).for_each(|_| camera.update(dt, &ship, &mut land));
+    }
+
+    fn fixed(position: Point, zoom: f32) -> Camera {
+        let mut camera = Camera::new();
+        camera.mode = CameraMode::Fixed { position, zoom };
+        camera.position_smoothing = 0.5;
+        camera
+    }
+
+    #[test]
+    fn smoothing_does_not_depend_on_framerate() {
+        let mut slow = fixed(Point(100.0, 50.0), 0.01);
+        let mut fast = fixed(Point(100.0, 50.0), 0.01);
+        update(&mut slow, 1.0 / 20.0, 20);
+        update(&mut fast, 1.0 / 200.0, 200);
+
+        assert!((slow.position() - fast.position()).len() < 0.01);
+        assert!((slow.zoom() - fast.zoom()).abs() < 0.00001);
+        assert!(slow.position().0 > 80.0 && slow.position().0 < 100.0);
+    }
+
+    #[test]
+    fn zoom_stays_within_limits() {
+        let mut close = fixed(Point::zero(), 1.0);
+        update(&mut close, 0.1, 200);
+        assert!((close.zoom() - close.max_zoom).abs() < 0.00001);
+
+        let mut far = fixed(Point::zero(), 0.0);
+        far.zoom = 0.01;
+        update(&mut far, 0.1, 200);
+        assert!((far.zoom() - far.min_zoom).abs() < 0.00001);
+    }
+
+    #[test]
+    fn shake_dies_down() {
+        let mut camera = fixed(Point::zero(), 0.01);
+        camera.position_smoothing = 0.0;
+        camera.shake(10.0);
+        update(&mut camera, 0.01, 1);
+        assert!(camera.shake > 9.0);
+        assert!(camera.shake_offset.0.abs() <= camera.shake);
+
+        update(&mut camera, 0.01, 99);
+        assert!(camera.shake < 10.0 * (-5.9f32).exp());
+        camera.shake(1.0);
+        assert_eq!(camera.shake, 1.0);
+    }
+}
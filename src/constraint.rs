@@ -0,0 +1,221 @@
+use std::f32::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    geom::{Point, Vector},
+    inertia::Inertia,
+    math,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Constraint {
+    /// Points `a` and `b` held `length` apart.
+    Distance { a: usize, b: usize, length: f32 },
+    /// Turn from `pivot`→`a` to `pivot`→`b` held at `angle`, counter
+    /// clockwise in radians.
+    Angle {
+        a: usize,
+        pivot: usize,
+        b: usize,
+        angle: f32,
+    },
+}
+
+/// Keeps a set of points in shape by moving them back into place after they
+/// integrate. The points are lent on every call, so the same solver can hold
+/// a hull, a rope or debris.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Solver {
+    constraints: Vec<Constraint>,
+    /// Inverse of each point's mass, zero for pinned ones.
+    weights: Vec<f32>,
+    /// Passes over every constraint per step. More passes hold the shape
+    /// tighter under strong forces.
+    pub iterations: u32,
+}
+impl Solver {
+    /// A solver for points of `masses`. An infinite mass pins its point.
+    pub fn new(masses: &[f32], iterations: u32) -> Solver {
+        Solver {
+            constraints: Vec::new(),
+            weights: masses.iter().map(|&mass| 1.0 / mass).collect(),
+            iterations,
+        }
+    }
+
+    pub fn constraints(&self) -> &[Constraint] {
+        &self.constraints
+    }
+
+    pub fn add(&mut self, constraint: Constraint) {
+        self.constraints.push(constraint);
+    }
+
+    /// Holds `a` and `b` at the distance they are apart in `points`.
+    pub fn keep_distance(&mut self, points: &[Point], a: usize, b: usize) {
+        let length = (points[b] - points[a]).len();
+        self.add(Constraint::Distance { a, b, length });
+    }
+
+    /// Holds the angle `a`, `pivot` and `b` make in `points`.
+    pub fn keep_angle(&mut self, points: &[Point], a: usize, pivot: usize, b: usize) {
+        let angle = Self::angle(points[a] - points[pivot], points[b] - points[pivot]);
+        self.add(Constraint::Angle { a, pivot, b, angle });
+    }
+
+    /// Runs every pass. Each one works out all the corrections from where the
+    /// points are before moving any, averaging them on points shared by
+    /// several constraints, so the order constraints were added in does not
+    /// matter and symmetric shapes stay symmetric.
+    pub fn solve(&self, points: &mut [Inertia]) {
+        let mut moves = vec![(Point::zero(), 0.0); points.len()];
+        for _ in 0..self.iterations {
+            for constraint in &self.constraints {
+                for &(point, offset) in &self.correction(points, constraint) {
+                    let (total, count) = &mut moves[point];
+                    *total = *total + offset;
+                    *count += 1.0;
+                }
+            }
+            for (point, (total, count)) in points.iter_mut().zip(moves.iter_mut()) {
+                if *count > 0.0 {
+                    point.position = point.position + *total * (1.0 / *count);
+                }
+                *total = Point::zero();
+                *count = 0.0;
+            }
+        }
+    }
+
+    /// How far off the worst constraint is, in units for distances and
+    /// radians for angles.
+    pub fn error(&self, points: &[Inertia]) -> f32 {
+        self.constraints
+            .iter()
+            .map(|constraint| match *constraint {
+                Constraint::Distance { a, b, length } => {
+                    ((points[b].position - points[a].position).len() - length).abs()
+                }
+                Constraint::Angle { a, pivot, b, angle } => {
+                    let pivot_at = points[pivot].position;
+                    let current =
+                        Self::angle(points[a].position - pivot_at, points[b].position - pivot_at);
+                    Self::wrap(angle - current).abs()
+                }
+            })
+            .fold(0.0, f32::max)
+    }
+
+    /// Offsets moving the points of `constraint` back in place, each in
+    /// proportion to how light it is.
+    fn correction(&self, points: &[Inertia], constraint: &Constraint) -> [(usize, Vector); 2] {
+        match *constraint {
+            Constraint::Distance { a, b, length } => {
+                let (wa, wb) = (self.weights[a], self.weights[b]);
+                let delta = points[b].position - points[a].position;
+                let len = delta.len();
+                if len == 0.0 || wa + wb == 0.0 {
+                    return [(a, Point::zero()), (b, Point::zero())];
+                }
+
+                let push = delta * ((len - length) / (len * (wa + wb)));
+                [(a, push * wa), (b, push * -wb)]
+            }
+            Constraint::Angle { a, pivot, b, angle } => {
+                let (wa, wb) = (self.weights[a], self.weights[b]);
+                let pivot_at = points[pivot].position;
+                let (arm_a, arm_b) = (points[a].position - pivot_at, points[b].position - pivot_at);
+                if arm_a.len() == 0.0 || arm_b.len() == 0.0 || wa + wb == 0.0 {
+                    return [(a, Point::zero()), (b, Point::zero())];
+                }
+
+                // Turns both ends around the pivot, the lighter one further.
+                let turn = Self::wrap(angle - Self::angle(arm_a, arm_b)) / (wa + wb);
+                [
+                    (a, Self::turned(arm_a, -turn * wa) - arm_a),
+                    (b, Self::turned(arm_b, turn * wb) - arm_b),
+                ]
+            }
+        }
+    }
+
+    fn angle(from: Vector, to: Vector) -> f32 {
+        let cross = from.0 * to.1 - from.1 * to.0;
+        math::atan2(cross, from.dot(to))
+    }
+
+    fn turned(arm: Vector, angle: f32) -> Vector {
+        let (s, c) = (math::sin(angle), math::cos(angle));
+        Point(arm.0 * c - arm.1 * s, arm.0 * s + arm.1 * c)
+    }
+
+    fn wrap(angle: f32) -> f32 {
+        if angle > PI {
+            angle - 2.0 * PI
+        } else if angle < -PI {
+            angle + 2.0 * PI
+        } else {
+            angle
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn inertias(points: &[Point]) -> Vec<Inertia> {
+        points.iter().map(|&point| Inertia::new(point)).collect()
+    }
+
+    #[test]
+    fn constraints_pull_points_back_in_place() {
+        let rest = [Point(0.0, 0.0), Point(4.0, 0.0), Point(4.0, 3.0)];
+        let mut solver = Solver::new(&[1.0, 1.0, f32::INFINITY], 20);
+        solver.keep_distance(&rest, 0, 1);
+        solver.keep_angle(&rest, 0, 1, 2);
+
+        let mut points = inertias(&rest);
+        points[0].position = Point(-2.0, 1.0);
+        points[1].position = Point(5.0, -1.0);
+        assert!(solver.error(&points) > 1.0);
+
+        solver.solve(&mut points);
+        assert!(solver.error(&points) < 0.001);
+        assert_eq!(points[2].position, rest[2]);
+    }
+
+    #[test]
+    fn heavier_points_move_less() {
+        let rest = [Point(0.0, 0.0), Point(1.0, 0.0)];
+        let mut solver = Solver::new(&[1.0, 3.0], 1);
+        solver.keep_distance(&rest, 0, 1);
+
+        let mut points = inertias(&[Point(0.0, 0.0), Point(5.0, 0.0)]);
+        solver.solve(&mut points);
+        assert!((points[0].position - Point(3.0, 0.0)).len() < 0.0001);
+        assert!((points[1].position - Point(4.0, 0.0)).len() < 0.0001);
+    }
+
+    #[test]
+    fn shapes_hold_under_large_forces() {
+        let rest = [Point(-3.0, 0.0), Point(3.0, 0.0), Point(0.0, 10.0)];
+        let mut solver = Solver::new(&[1.0, 1.0, 2.0], 10);
+        solver.keep_distance(&rest, 0, 1);
+        solver.keep_distance(&rest, 1, 2);
+        solver.keep_distance(&rest, 2, 0);
+
+        let mut points = inertias(&rest);
+        for _ in 0..200 {
+            points[0].force(Point(0.0, 50.0));
+            points[1].force(Point(-20.0, 0.0));
+            points.iter_mut().for_each(Inertia::integrate);
+            solver.solve(&mut points);
+            assert!(solver.error(&points) < 0.01);
+        }
+        assert!(points
+            .iter()
+            .all(|point| point.position.0.is_finite() && point.position.1.is_finite()));
+    }
+}
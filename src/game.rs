@@ -0,0 +1,1119 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::Arc,
+};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::{
+    geom::{Point, Vector},
+    hash::{StateHash, StateHasher},
+    replay::{Input, Replay},
+    score::{Breakdown, Run, ScoreRules, Touchdown},
+    ship::{definition::ShipDefinition, Impact, Ship, Throttle, ThrusterModel},
+    sling::Sling,
+    terrain::{generator::MidpointDisplacement, land::Land, Pad, Terrain},
+};
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum State {
+    Title,
+    Countdown,
+    Flying,
+    Landed,
+    Crashed,
+    GameOver,
+}
+impl State {
+    pub fn ship_visible(&self) -> bool {
+        !matches!(self, State::Crashed | State::GameOver)
+    }
+}
+
+#[derive(
+    Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub struct ShipId(pub u32);
+impl ShipId {
+    pub const PLAYER: ShipId = ShipId(0);
+}
+
+/// What pilots fly for, the same for the whole session.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Mission {
+    /// Every touchdown scores.
+    #[default]
+    Landing,
+    /// Carry a cargo slung under the ship onto a pad further along the land,
+    /// then on to the next one.
+    Delivery,
+}
+impl Mission {
+    /// Pad the cargo is due on when setting off from `x`: the nearest one at
+    /// least `Game::delivery_distance()` to the right.
+    fn target(self, land: &mut dyn Terrain, x: f32) -> Option<Pad> {
+        if self != Mission::Delivery {
+            return None;
+        }
+        let from = x + Game::delivery_distance();
+        land.pads(from, from + 2000.0)
+            .into_iter()
+            .filter(|pad| pad.center.0 >= from)
+            .min_by(|a, b| a.center.0.partial_cmp(&b.center.0).unwrap())
+    }
+}
+
+/// One ship going through the game flow. The land is lent on every call so
+/// several pilots can fly over the same terrain.
+pub struct Pilot {
+    state: State,
+    timer: u64,
+    lives: u32,
+    airborne: bool,
+    resting: u64,
+    grounded: bool,
+    touchdown: Option<Touchdown>,
+    run: Run,
+    ship: Ship,
+    sling: Option<Sling>,
+    craft: Arc<ShipDefinition>,
+    mission: Mission,
+    target: Option<Pad>,
+    home: f32,
+    ghost: bool,
+    /// Seed of the session, so thrusters fail differently from one to the
+    /// next.
+    session: u64,
+    events: VecDeque<State>,
+}
+impl Pilot {
+    fn new(
+        land: &mut dyn Terrain,
+        home: f32,
+        ghost: bool,
+        craft: Arc<ShipDefinition>,
+        mission: Mission,
+        session: u64,
+    ) -> Pilot {
+        let mut pilot = Pilot {
+            state: State::Title,
+            timer: 0,
+            lives: Game::max_lives(),
+            airborne: false,
+            resting: 0,
+            grounded: true,
+            touchdown: None,
+            run: Run::new(),
+            ship: Self::place(land, home, &craft, session),
+            sling: None,
+            craft,
+            mission,
+            target: None,
+            home,
+            ghost,
+            session,
+            events: VecDeque::from(vec![State::Title]),
+        };
+        pilot.equip(land);
+        pilot
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Ticks spent in the current state.
+    pub fn timer(&self) -> u64 {
+        self.timer
+    }
+
+    pub fn lives(&self) -> u32 {
+        self.lives
+    }
+
+    pub fn run(&self) -> &Run {
+        &self.run
+    }
+
+    pub fn ship(&self) -> &Ship {
+        &self.ship
+    }
+
+    pub fn sling(&self) -> Option<&Sling> {
+        self.sling.as_ref()
+    }
+
+    /// Pad the cargo is due on.
+    pub fn target(&self) -> Option<Pad> {
+        self.target
+    }
+
+    /// Ghosts fly over a land of their own, so their craters never touch the
+    /// live game's.
+    pub fn is_ghost(&self) -> bool {
+        self.ghost
+    }
+
+    fn accepts(&self, level: f32) -> bool {
+        level > 0.0 && self.timer >= Game::input_delay()
+    }
+
+    fn control(&mut self, land: &mut dyn Terrain, throttle: Throttle, level: f32) {
+        match self.state {
+            State::Flying => self.ship.throttle(throttle, level),
+            _ if level <= 0.0 => {}
+            State::Title => self.enter(State::Countdown),
+            State::Countdown => {}
+            _ if !self.accepts(level) => {}
+            State::Landed => self.enter(State::Countdown),
+            State::Crashed if self.lives == 0 => self.enter(State::GameOver),
+            State::Crashed => {
+                let x = self.ship.origin().0;
+                self.spawn(land, x);
+                self.enter(State::Countdown);
+            }
+            State::GameOver => self.restart(land),
+        }
+    }
+
+    fn restart(&mut self, land: &mut dyn Terrain) {
+        self.lives = Game::max_lives();
+        self.run = Run::new();
+        self.spawn(land, self.home);
+        self.enter(State::Title);
+    }
+
+    fn advance(
+        &mut self,
+        land: &mut dyn Terrain,
+        rules: &ScoreRules,
+        thrusters: &ThrusterModel,
+    ) -> Option<Impact> {
+        self.timer += 1;
+        if !self.state.ship_visible() {
+            return None;
+        }
+
+        let impact = self.ship.integrate(land, thrusters);
+        if let Some(sling) = &mut self.sling {
+            sling.update(land, &mut self.ship);
+        }
+        match self.state {
+            State::Countdown if self.timer >= Game::countdown() => {
+                self.airborne = false;
+                self.resting = 0;
+                self.grounded = true;
+                self.touchdown = None;
+                self.enter(State::Flying);
+            }
+            State::Flying => self.fly(land, rules, impact),
+            _ => {}
+        }
+        impact
+    }
+
+    fn fly(&mut self, land: &mut dyn Terrain, rules: &ScoreRules, impact: Option<Impact>) {
+        let crash = impact.filter(|impact| impact.speed() > Ship::crash_speed());
+        if crash.is_some() || self.ship.capsized() {
+            if let Some(impact) = crash {
+                let radius = (impact.speed() * 0.5).min(30.0);
+                land.deform(impact.position, radius);
+            }
+            self.crash();
+            return;
+        }
+
+        let grounded = self.ship.grounded();
+        if !grounded {
+            self.airborne = true;
+        }
+        if let Some(impact) = impact.filter(|_| grounded && !self.grounded) {
+            self.touchdown = Some(Touchdown {
+                velocity: impact.velocity,
+                attitude: self.ship.attitude(),
+                position: self.ship.origin(),
+                pad: None,
+                fuel: 0.0,
+                delivered: false,
+            });
+        }
+        self.grounded = grounded;
+
+        if self.ship.resting() {
+            self.resting += 1;
+        } else {
+            self.resting = 0;
+        }
+        if self.airborne && self.resting >= Game::settle() {
+            self.touch_down(land, rules);
+        }
+    }
+
+    fn crash(&mut self) {
+        self.lives = self.lives.saturating_sub(1);
+        self.ship.release();
+        self.enter(State::Crashed);
+    }
+
+    /// Pushes the ship out of another one it ran into, wrecking it if the hit
+    /// was hard enough and it was in flight.
+    fn collide(&mut self, push: Vector, hard: bool) {
+        if hard && self.state == State::Flying {
+            self.crash();
+        } else {
+            self.ship.nudge(push);
+        }
+    }
+
+    fn touch_down(&mut self, land: &mut dyn Terrain, rules: &ScoreRules) {
+        let mut touchdown = self.touchdown.unwrap_or(Touchdown {
+            velocity: self.ship.velocity(),
+            attitude: self.ship.attitude(),
+            position: self.ship.origin(),
+            pad: None,
+            fuel: 0.0,
+            delivered: false,
+        });
+        touchdown.position = self.ship.origin();
+        touchdown.pad = land.pad(touchdown.position.0);
+        touchdown.fuel = self.ship.fuel();
+        touchdown.delivered = self.deliver(land);
+        let pad = touchdown.pad;
+        self.run.record(rules.score(&touchdown));
+
+        self.ship.release();
+        if pad.is_some() {
+            self.ship.refuel();
+            self.ship.repair();
+        }
+        self.enter(State::Landed);
+    }
+
+    /// Hands the cargo over if it sits on the target pad, and picks the next
+    /// pad along for the following one.
+    fn deliver(&mut self, land: &mut dyn Terrain) -> bool {
+        let cargo = match &self.sling {
+            Some(sling) if sling.landed() => sling.cargo(),
+            _ => return false,
+        };
+        match self.target {
+            Some(target) if target.contains(cargo.0) => {
+                self.target = self.mission.target(land, target.center.0);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn enter(&mut self, state: State) {
+        self.state = state;
+        self.timer = 0;
+        self.events.push_back(state);
+    }
+
+    /// Brings a new ship in near `x`, with the mission's cargo if it has one.
+    fn spawn(&mut self, land: &mut dyn Terrain, x: f32) {
+        self.ship = Self::place(land, x, &self.craft, self.session);
+        self.equip(land);
+    }
+
+    /// Hangs the mission's cargo under a freshly placed ship and picks where
+    /// it is due.
+    fn equip(&mut self, land: &mut dyn Terrain) {
+        self.sling = match self.mission {
+            Mission::Delivery => Some(Sling::new(land, &self.ship)),
+            Mission::Landing => None,
+        };
+        self.target = self.mission.target(land, self.ship.origin().0);
+    }
+
+    /// Places a ship on the pad nearest to `x`, or right at `x` when there is
+    /// none around.
+    fn place(land: &mut dyn Terrain, x: f32, craft: &Arc<ShipDefinition>, session: u64) -> Ship {
+        let pad = land
+            .pads(x - 1000.0, x + 1000.0)
+            .into_iter()
+            .min_by(|a, b| {
+                let a = (a.center.0 - x).abs();
+                let b = (b.center.0 - x).abs();
+                a.partial_cmp(&b).unwrap()
+            });
+        let mut ship = match pad {
+            Some(pad) => Ship::with_craft(craft.clone(), pad.center),
+            None => Ship::with_craft(craft.clone(), Point(x, land.height(x))),
+        };
+        ship.reseed(session);
+        ship
+    }
+}
+
+impl StateHash for Pilot {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        hasher.write_u32(self.state as u32);
+        hasher.write_u64(self.timer);
+        hasher.write_u32(self.lives);
+        hasher.write_bool(self.airborne);
+        hasher.write_u64(self.resting);
+        hasher.write_bool(self.grounded);
+        hasher.write_u32(self.run.total());
+        self.ship.hash_state(hasher);
+        if let Some(sling) = &self.sling {
+            sling.hash_state(hasher);
+        }
+    }
+}
+
+/// A pilot playing back the inputs of an earlier run, in step with the live
+/// game's tick. It flies over its own copy of the land, so the craters it
+/// left in the recorded run come back and the live ones stay out of its way.
+/// Only the player's inputs are played back; other ships of the run are not
+/// part of its score and stay out of the ghost.
+pub struct Ghost {
+    pilot: Pilot,
+    replay: Replay,
+    next: usize,
+    land: Box<dyn Terrain>,
+}
+impl Ghost {
+    fn new(replay: Replay) -> Ghost {
+        let mut land = Game::seeded_land(replay.seed);
+        Ghost {
+            pilot: Pilot::new(
+                land.as_mut(),
+                0.0,
+                true,
+                Arc::new(replay.craft.clone()),
+                replay.mission,
+                replay.seed,
+            ),
+            replay,
+            next: 0,
+            land,
+        }
+    }
+
+    pub fn pilot(&self) -> &Pilot {
+        &self.pilot
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    pub fn finished(&self, tick: u64) -> bool {
+        tick >= self.replay.ticks
+    }
+
+    pub fn visible(&self, tick: u64) -> bool {
+        !self.finished(tick) && self.pilot.state().ship_visible()
+    }
+
+    fn advance(&mut self, tick: u64, rules: &ScoreRules) {
+        if self.finished(tick) {
+            return;
+        }
+        let land = self.land.as_mut();
+        while let Some(&Input {
+            tick: at,
+            ship,
+            throttle,
+            level,
+        }) = self.replay.inputs.get(self.next)
+        {
+            if at > tick {
+                break;
+            }
+            if ship == ShipId::PLAYER {
+                self.pilot.control(land, throttle, level);
+            }
+            self.next += 1;
+        }
+        self.pilot.advance(land, rules, &self.replay.thrusters);
+        self.pilot.events.clear();
+    }
+}
+
+pub struct Game {
+    pub rules: ScoreRules,
+    tick: u64,
+    replay: Option<Replay>,
+    pilots: BTreeMap<ShipId, Pilot>,
+    next_id: u32,
+    collisions: bool,
+    thrusters: ThrusterModel,
+    craft: Arc<ShipDefinition>,
+    mission: Mission,
+    best: Vec<(u32, Replay)>,
+    ghosts: Vec<Ghost>,
+    land: Box<dyn Terrain>,
+}
+impl Game {
+    pub fn new(land: Box<dyn Terrain>) -> Game {
+        Self::recording(land, None)
+    }
+
+    /// A game on procedurally generated land that records a replay of itself.
+    pub fn with_seed(seed: u64) -> Game {
+        Self::recording(Self::seeded_land(seed), Some(Replay::new(seed)))
+    }
+
+    fn recording(mut land: Box<dyn Terrain>, replay: Option<Replay>) -> Game {
+        let craft = Arc::new(ShipDefinition::default());
+        let session = replay.as_ref().map_or(0, |replay| replay.seed);
+        let mut pilots = BTreeMap::new();
+        let player = Pilot::new(
+            land.as_mut(),
+            0.0,
+            false,
+            craft.clone(),
+            Mission::default(),
+            session,
+        );
+        pilots.insert(ShipId::PLAYER, player);
+        Game {
+            rules: ScoreRules::default(),
+            tick: 0,
+            replay,
+            pilots,
+            next_id: 1,
+            collisions: false,
+            thrusters: ThrusterModel::default(),
+            craft,
+            mission: Mission::default(),
+            best: Vec::new(),
+            ghosts: Vec::new(),
+            land,
+        }
+    }
+
+    pub fn max_lives() -> u32 {
+        3
+    }
+
+    /// How far along the land a cargo is due from where it is picked up.
+    pub fn delivery_distance() -> f32 {
+        300.0
+    }
+
+    /// Ticks between the countdown starting and the player taking control.
+    pub fn countdown() -> u64 {
+        300
+    }
+
+    /// Ticks the ship must rest on its legs before a touchdown counts as landed.
+    pub fn settle() -> u64 {
+        50
+    }
+
+    /// Ticks before a landed, crashed or game over screen accepts input, so a
+    /// held key does not skip it.
+    pub fn input_delay() -> u64 {
+        100
+    }
+
+    /// How many of the best earlier runs keep flying alongside the player.
+    pub fn max_ghosts() -> usize {
+        3
+    }
+
+    /// Distance between the spots where consecutive ships are based.
+    pub fn ship_spacing() -> f32 {
+        700.0
+    }
+
+    pub fn state(&self) -> State {
+        self.player().state()
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Ticks spent in the current state.
+    pub fn timer(&self) -> u64 {
+        self.player().timer()
+    }
+
+    pub fn lives(&self) -> u32 {
+        self.player().lives()
+    }
+
+    pub fn run(&self) -> &Run {
+        self.player().run()
+    }
+
+    pub fn last_landing(&self) -> Option<&Breakdown> {
+        self.player().run().last()
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.replay.as_ref().map(|replay| replay.seed)
+    }
+
+    pub fn replay(&self) -> Option<Replay> {
+        self.replay.clone().map(|mut replay| {
+            replay.ticks = self.tick;
+            replay
+        })
+    }
+
+    pub fn collisions(&self) -> bool {
+        self.collisions
+    }
+
+    /// Makes ships bounce off, or wreck, each other. It is a setting for the
+    /// whole session, so changing it starts a new one.
+    pub fn set_collisions(&mut self, collisions: bool) {
+        self.collisions = collisions;
+        self.renew();
+    }
+
+    pub fn thrusters(&self) -> &ThrusterModel {
+        &self.thrusters
+    }
+
+    /// Changes how every ship's thrusters behave, starting a new session too.
+    pub fn set_thrusters(&mut self, thrusters: ThrusterModel) {
+        self.thrusters = thrusters;
+        self.renew();
+    }
+
+    pub fn craft(&self) -> &ShipDefinition {
+        &self.craft
+    }
+
+    /// Starts a new session with every ship as a new `craft`.
+    pub fn set_craft(&mut self, craft: ShipDefinition) {
+        self.craft = Arc::new(craft);
+        let craft = &self.craft;
+        self.pilots
+            .values_mut()
+            .for_each(|pilot| pilot.craft = craft.clone());
+        self.renew();
+    }
+
+    pub fn mission(&self) -> Mission {
+        self.mission
+    }
+
+    /// Starts a new session with every ship out on `mission`.
+    pub fn set_mission(&mut self, mission: Mission) {
+        self.mission = mission;
+        self.pilots
+            .values_mut()
+            .for_each(|pilot| pilot.mission = mission);
+        self.renew();
+    }
+
+    pub fn player(&self) -> &Pilot {
+        &self.pilots[&ShipId::PLAYER]
+    }
+
+    pub fn ship(&self) -> &Ship {
+        self.player().ship()
+    }
+
+    pub fn pilot(&self, id: ShipId) -> Option<&Pilot> {
+        self.pilots.get(&id)
+    }
+
+    pub fn pilots(&self) -> impl Iterator<Item = (ShipId, &Pilot)> + '_ {
+        self.pilots.iter().map(|(&id, pilot)| (id, pilot))
+    }
+
+    pub fn ghosts(&self) -> impl Iterator<Item = &Ghost> + '_ {
+        self.ghosts.iter()
+    }
+
+    pub fn land(&mut self) -> &mut dyn Terrain {
+        self.land.as_mut()
+    }
+
+    pub fn poll_event(&mut self) -> Option<State> {
+        self.poll_ship_event(ShipId::PLAYER)
+    }
+
+    pub fn poll_ship_event(&mut self, id: ShipId) -> Option<State> {
+        self.pilots.get_mut(&id)?.events.pop_front()
+    }
+
+    /// Adds a ship based a bit further along the land than the last one.
+    pub fn add_ship(&mut self) -> ShipId {
+        let id = ShipId(self.next_id);
+        self.join(id);
+        id
+    }
+
+    /// Takes a ship out of the game. The player's ship always stays.
+    pub fn remove_ship(&mut self, id: ShipId) -> bool {
+        if id == ShipId::PLAYER || self.pilots.remove(&id).is_none() {
+            return false;
+        }
+        if let Some(replay) = &mut self.replay {
+            replay.membership(self.tick, id, false);
+        }
+        true
+    }
+
+    pub(crate) fn join(&mut self, id: ShipId) {
+        let home = id.0 as f32 * Self::ship_spacing();
+        let craft = self.craft.clone();
+        let session = self.seed().unwrap_or(0);
+        let pilot = Pilot::new(
+            self.land.as_mut(),
+            home,
+            false,
+            craft,
+            self.mission,
+            session,
+        );
+        self.pilots.insert(id, pilot);
+        self.next_id = self.next_id.max(id.0 + 1);
+        if let Some(replay) = &mut self.replay {
+            replay.membership(self.tick, id, true);
+        }
+    }
+
+    /// Keeps an earlier run on this game's seed among the best ones, flying it
+    /// as a ghost caught up to the current tick. Replays from other seeds or
+    /// older versions are refused.
+    pub fn add_ghost(&mut self, score: u32, replay: Replay) -> bool {
+        if self.seed() != Some(replay.seed) || !replay.is_current() {
+            return false;
+        }
+
+        let rank = self.best.partition_point(|(other, _)| *other >= score);
+        if rank >= Self::max_ghosts() {
+            return false;
+        }
+        self.best.insert(rank, (score, replay.clone()));
+        self.best.truncate(Self::max_ghosts());
+
+        let mut ghost = Ghost::new(replay);
+        for tick in 0..self.tick {
+            ghost.advance(tick, &self.rules);
+        }
+        self.ghosts.insert(rank, ghost);
+        self.ghosts.truncate(Self::max_ghosts());
+        true
+    }
+
+    pub fn control(&mut self, throttle: Throttle, level: f32) {
+        self.control_ship(ShipId::PLAYER, throttle, level);
+    }
+
+    pub fn control_ship(&mut self, id: ShipId, throttle: Throttle, level: f32) {
+        if !self.pilots.contains_key(&id) {
+            return;
+        }
+        if let Some(replay) = &mut self.replay {
+            replay.record(self.tick, id, throttle, level);
+        }
+
+        let pilot = self.pilots.get_mut(&id).unwrap();
+        if id == ShipId::PLAYER && pilot.state() == State::GameOver && pilot.accepts(level) {
+            self.restart();
+        } else {
+            pilot.control(self.land.as_mut(), throttle, level);
+        }
+    }
+
+    /// Starts a new run for every ship. Seeded games also regrow their land and
+    /// restart the replay, so every run can be played back on its own, and the
+    /// player's finished run joins the ghosts if it is among the best.
+    pub fn restart(&mut self) {
+        let finished = self.replay().map(|replay| (self.run().total(), replay));
+        self.renew();
+        if let Some((score, replay)) = finished {
+            self.add_ghost(score, replay);
+        }
+    }
+
+    /// Starts over with the current settings, on fresh land and with a new
+    /// replay, flying against the best runs so far.
+    fn renew(&mut self) {
+        if let Some(seed) = self.seed() {
+            self.land = Self::seeded_land(seed);
+            let mut replay = Replay::new(seed);
+            replay.collisions = self.collisions;
+            replay.thrusters = self.thrusters;
+            replay.craft = (*self.craft).clone();
+            replay.mission = self.mission;
+            self.pilots
+                .keys()
+                .filter(|&&id| id != ShipId::PLAYER)
+                .for_each(|&id| replay.membership(0, id, true));
+            self.replay = Some(replay);
+            self.tick = 0;
+        }
+        let land = self.land.as_mut();
+        self.pilots
+            .values_mut()
+            .for_each(|pilot| pilot.restart(land));
+
+        self.ghosts.clear();
+        let best = std::mem::take(&mut self.best);
+        best.into_iter().for_each(|(score, replay)| {
+            self.add_ghost(score, replay);
+        });
+    }
+
+    /// Runs one tick and returns how each ship that hit the ground did.
+    pub fn advance(&mut self) -> Vec<(ShipId, Impact)> {
+        let (tick, rules) = (self.tick, &self.rules);
+        self.ghosts
+            .iter_mut()
+            .for_each(|ghost| ghost.advance(tick, rules));
+        self.tick += 1;
+
+        let mut impacts = Vec::new();
+        for (&id, pilot) in self.pilots.iter_mut() {
+            if let Some(impact) = pilot.advance(self.land.as_mut(), &self.rules, &self.thrusters) {
+                impacts.push((id, impact));
+            }
+        }
+        if self.collisions {
+            self.collide();
+        }
+        impacts
+    }
+
+    fn collide(&mut self) {
+        let solid: Vec<ShipId> = self
+            .pilots
+            .iter()
+            .filter(|(_, pilot)| pilot.state().ship_visible())
+            .map(|(&id, _)| id)
+            .collect();
+
+        for (idx, a) in solid.iter().enumerate() {
+            for b in &solid[idx + 1..] {
+                let (ship_a, ship_b) = (self.pilots[a].ship(), self.pilots[b].ship());
+                if !ship_a.touches(ship_b) {
+                    continue;
+                }
+
+                let hard = (ship_a.velocity() - ship_b.velocity()).len() > Ship::crash_speed();
+                let push = (ship_a.center() - ship_b.center()).unit() * 0.5;
+                self.pilots.get_mut(a).unwrap().collide(push, hard);
+                self.pilots.get_mut(b).unwrap().collide(push * -1.0, hard);
+            }
+        }
+    }
+
+    /// Half the width of the land hashed around each ship.
+    pub fn hash_window() -> f32 {
+        200.0
+    }
+
+    /// Stable hash of everything that decides how the game goes on: the tick,
+    /// every pilot and ghost, and the land around each of their ships, a
+    /// ghost's own land for ghosts.
+    pub fn state_hash(&mut self) -> u64 {
+        let mut hasher = StateHasher::default();
+        hasher.write_u64(self.tick);
+        for (id, pilot) in &self.pilots {
+            hasher.write_u32(id.0);
+            pilot.hash_state(&mut hasher);
+        }
+        for ghost in &self.ghosts {
+            ghost.pilot.hash_state(&mut hasher);
+        }
+
+        let window = Self::hash_window();
+        let mut hash_land = |land: &mut dyn Terrain, center: f32| {
+            land.segments(center - window, center + window)
+                .iter()
+                .for_each(|segment| segment.hash_state(&mut hasher));
+        };
+        for pilot in self.pilots.values() {
+            hash_land(self.land.as_mut(), pilot.ship.origin().0);
+        }
+        for ghost in &mut self.ghosts {
+            hash_land(ghost.land.as_mut(), ghost.pilot.ship.origin().0);
+        }
+        hasher.finish()
+    }
+
+    fn seeded_land(seed: u64) -> Box<dyn Terrain> {
+        Box::new(Land::with_generator(Box::new(MidpointDisplacement::new(
+            seed,
+        ))))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::terrain::polyline::Polyline;
+
+    fn game() -> Game {
+        let flat = Polyline::new(vec![Point(-1000.0, 0.0), Point(1000.0, 0.0)]);
+        Game::new(Box::new(flat))
+    }
+
+    fn run(game: &mut Game, ticks: u64) {
+        (0..ticks).for_each(|_| {
+            game.advance();
+        });
+    }
+
+    fn events(game: &mut Game) -> Vec<State> {
+        std::iter::from_fn(|| game.poll_event()).collect()
+    }
+
+    #[test]
+    fn countdown_then_landing() {
+        let mut game = game();
+        run(&mut game, 100);
+        assert_eq!(game.state(), State::Title);
+
+        game.control(Throttle::Bottom, 1.0);
+        run(&mut game, Game::countdown());
+        assert_eq!(game.state(), State::Flying);
+
+        game.control(Throttle::Bottom, 1.0);
+        run(&mut game, 50);
+        game.control(Throttle::Bottom, 0.0);
+        run(&mut game, 1000);
+        assert_eq!(game.state(), State::Landed);
+        assert_eq!(game.run().landings().len(), 1);
+        assert!(game.ship().fuel() < game.ship().max_fuel());
+        assert_eq!(
+            events(&mut game),
+            vec![State::Title, State::Countdown, State::Flying, State::Landed]
+        );
+    }
+
+    #[test]
+    fn crashes_until_game_over() {
+        let mut game = game();
+        game.control(Throttle::Bottom, 1.0);
+        for lives in (0..Game::max_lives()).rev() {
+            run(&mut game, Game::countdown());
+            game.control(Throttle::Bottom, 1.0);
+            run(&mut game, 300);
+            game.control(Throttle::Bottom, 0.0);
+            run(&mut game, 1000);
+            assert_eq!(game.state(), State::Crashed);
+            assert_eq!(game.lives(), lives);
+            game.control(Throttle::Bottom, 1.0);
+        }
+        assert_eq!(game.state(), State::GameOver);
+
+        run(&mut game, Game::input_delay());
+        game.control(Throttle::Left, 1.0);
+        assert_eq!(game.state(), State::Title);
+        assert_eq!(game.lives(), Game::max_lives());
+    }
+
+    #[test]
+    fn ghost_follows_the_recorded_run() {
+        let script = |game: &mut Game| {
+            game.control(Throttle::Bottom, 1.0);
+            run(game, Game::countdown());
+            game.control(Throttle::Bottom, 1.0);
+            run(game, 50);
+            game.control(Throttle::Bottom, 0.0);
+        };
+        let mut recorded = Game::with_seed(5);
+        script(&mut recorded);
+        run(&mut recorded, 1000);
+        let replay = recorded.replay().unwrap();
+
+        let mut game = Game::with_seed(5);
+        assert!(!game.add_ghost(10, Replay::new(6)));
+        let outdated = Replay {
+            version: 0,
+            ..replay.clone()
+        };
+        assert!(!game.add_ghost(10, outdated));
+        assert!(game.add_ghost(recorded.run().total(), replay));
+        script(&mut game);
+        for _ in 0..1000 {
+            let ghost = game.ghosts().next().unwrap();
+            assert_eq!(ghost.pilot().state(), game.state());
+            assert_eq!(ghost.pilot().ship().origin().0, game.ship().origin().0);
+            assert_eq!(ghost.pilot().ship().origin().1, game.ship().origin().1);
+            game.advance();
+        }
+        assert!(game.ghosts().all(|ghost| ghost.finished(game.tick())));
+        assert_eq!(game.state(), State::Landed);
+    }
+
+    #[test]
+    fn ghost_crashes_on_its_own_land_and_flies_on() {
+        let crash_then_land = |game: &mut Game| {
+            game.control(Throttle::Bottom, 1.0);
+            run(game, Game::countdown());
+            game.control(Throttle::Bottom, 1.0);
+            run(game, 300);
+            game.control(Throttle::Bottom, 0.0);
+            run(game, 1000);
+            assert_eq!(game.state(), State::Crashed);
+            game.control(Throttle::Bottom, 1.0);
+            run(game, Game::countdown());
+            game.control(Throttle::Bottom, 1.0);
+            run(game, 50);
+            game.control(Throttle::Bottom, 0.0);
+            run(game, 1000);
+        };
+        let mut recorded = Game::with_seed(5);
+        crash_then_land(&mut recorded);
+        assert_eq!(recorded.state(), State::Landed);
+        let replay = recorded.replay().unwrap();
+        let mut path = Vec::new();
+        replay.simulate_with(&recorded.rules, |game| path.push(game.ship().origin()));
+
+        // The live player stays at its base, leaving the land untouched.
+        let mut game = Game::with_seed(5);
+        assert!(game.add_ghost(recorded.run().total(), replay));
+        while !game.ghosts().all(|ghost| ghost.finished(game.tick())) {
+            let ghost = game.ghosts().next().unwrap().pilot().ship().origin();
+            assert_eq!(ghost, path[game.tick() as usize]);
+            game.advance();
+        }
+        assert_eq!(game.state(), State::Title);
+        let ghost = game.ghosts().next().unwrap().pilot();
+        assert_eq!(ghost.state(), State::Landed);
+        assert_eq!(ghost.lives(), Game::max_lives() - 1);
+    }
+
+    #[test]
+    fn impacts_name_the_ship_that_hit() {
+        let mut game = Game::with_seed(9);
+        let wingman = game.add_ship();
+        game.control_ship(wingman, Throttle::Bottom, 1.0);
+        run(&mut game, Game::countdown());
+        game.control_ship(wingman, Throttle::Bottom, 1.0);
+        run(&mut game, 50);
+        game.control_ship(wingman, Throttle::Bottom, 0.0);
+
+        // The resting player touches the ground too, but only softly.
+        let impacts: Vec<ShipId> = (0..1000)
+            .flat_map(|_| game.advance())
+            .filter(|(_, impact)| impact.speed() > 1.0)
+            .map(|(ship, _)| ship)
+            .collect();
+        assert_eq!(game.pilot(wingman).unwrap().state(), State::Landed);
+        assert!(impacts.contains(&wingman));
+        assert!(!impacts.contains(&ShipId::PLAYER));
+    }
+
+    #[test]
+    fn ships_fly_independently_and_replay() {
+        let mut game = Game::with_seed(9);
+        run(&mut game, 20);
+        let wingman = game.add_ship();
+        assert_ne!(wingman, ShipId::PLAYER);
+
+        game.control_ship(wingman, Throttle::Bottom, 1.0);
+        run(&mut game, Game::countdown());
+        game.control_ship(wingman, Throttle::Bottom, 1.0);
+        run(&mut game, 100);
+        assert_eq!(game.state(), State::Title);
+        assert_eq!(game.pilot(wingman).unwrap().state(), State::Flying);
+        assert!(game.pilot(wingman).unwrap().ship().velocity().1 > 1.0);
+        assert_eq!(game.poll_ship_event(wingman), Some(State::Title));
+
+        let replay = game.replay().unwrap();
+        let simulated = replay.simulate(&game.rules);
+        let (ship, original) = (
+            simulated.pilot(wingman).unwrap().ship(),
+            game.pilot(wingman).unwrap().ship(),
+        );
+        assert_eq!(ship.origin().1, original.origin().1);
+
+        assert!(!game.remove_ship(ShipId::PLAYER));
+        assert!(game.remove_ship(wingman));
+        assert!(game.pilot(wingman).is_none());
+    }
+
+    #[test]
+    fn delivery_missions_carry_cargo_and_replay() {
+        let mut game = Game::with_seed(9);
+        assert!(game.player().sling().is_none());
+        game.set_mission(Mission::Delivery);
+        let pilot = game.player();
+        let target = pilot.target().unwrap();
+        assert!(target.center.0 >= pilot.ship().origin().0 + Game::delivery_distance());
+        assert!(pilot.sling().unwrap().landed());
+
+        game.control(Throttle::Bottom, 1.0);
+        run(&mut game, Game::countdown());
+        game.control(Throttle::Bottom, 1.0);
+        run(&mut game, 300);
+        let cargo = game.player().sling().unwrap().cargo();
+        assert!(cargo.1 > game.land().height(cargo.0) + 1.0);
+
+        let replay = game.replay().unwrap();
+        assert_eq!(replay.mission, Mission::Delivery);
+        let simulated = replay.simulate(&game.rules);
+        assert_eq!(simulated.player().sling().unwrap().cargo(), cargo);
+
+        game.set_mission(Mission::Landing);
+        assert!(game.player().sling().is_none());
+        assert!(game.player().target().is_none());
+    }
+
+    #[test]
+    fn cargo_on_the_target_pad_is_delivered() {
+        let mut game = Game::with_seed(9);
+        game.set_mission(Mission::Delivery);
+        let (rules, land) = (&game.rules, game.land.as_mut());
+        let pilot = game.pilots.get_mut(&ShipId::PLAYER).unwrap();
+        let target = pilot.target().unwrap();
+
+        pilot.touch_down(land, rules);
+        assert_eq!(pilot.run().last().unwrap().delivery, 0.0);
+        assert_eq!(pilot.target().unwrap().center, target.center);
+
+        let hook = Ship::spawn(Point(target.center.0 - Sling::length(), 0.0));
+        pilot.sling = Some(Sling::new(land, &hook));
+        pilot.touch_down(land, rules);
+        let breakdown = pilot.run().last().unwrap();
+        assert_eq!(breakdown.delivery, rules.delivery);
+        assert!(breakdown.total as f32 >= rules.delivery);
+        let next = pilot.target().unwrap();
+        assert!(next.center.0 >= target.center.0 + Game::delivery_distance());
+        assert_eq!(pilot.state(), State::Landed);
+    }
+
+    #[test]
+    fn changing_settings_midway_starts_a_new_session() {
+        let mut game = Game::with_seed(9);
+        game.control(Throttle::Bottom, 1.0);
+        run(&mut game, Game::countdown() + 50);
+        assert_eq!(game.state(), State::Flying);
+
+        game.set_thrusters(ThrusterModel::hard());
+        assert_eq!(game.tick(), 0);
+        assert_eq!(game.state(), State::Title);
+        assert_eq!(game.ghosts().count(), 0);
+        let replay = game.replay().unwrap();
+        assert_eq!(replay.thrusters, ThrusterModel::hard());
+        assert!(replay.inputs.is_empty());
+
+        game.control(Throttle::Bottom, 1.0);
+        run(&mut game, Game::countdown() + 50);
+        let simulated = game.replay().unwrap().simulate(&game.rules);
+        assert_eq!(simulated.ship().origin(), game.ship().origin());
+    }
+
+    #[test]
+    fn overlapping_ships_push_apart_when_colliding() {
+        let mut game = game();
+        let other = game.add_ship();
+        game.pilots.get_mut(&other).unwrap().ship = Ship::spawn(Point(4.0, 0.0));
+        run(&mut game, 10);
+        assert!(game.ship().touches(game.pilot(other).unwrap().ship()));
+
+        game.set_collisions(true);
+        game.pilots.get_mut(&other).unwrap().ship = Ship::spawn(Point(4.0, 0.0));
+        run(&mut game, 100);
+        assert!(!game.ship().touches(game.pilot(other).unwrap().ship()));
+        assert!(game.ship().origin().0 < game.pilot(other).unwrap().ship().origin().0);
+    }
+}
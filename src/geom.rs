@@ -1,6 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    hash::{StateHash, StateHasher},
+    math,
+};
+
 pub type Vector = Point;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Point(pub f32, pub f32);
 impl Point {
     pub fn dot(self, rhs: Point) -> f32 {
@@ -16,7 +23,7 @@ impl Point {
     }
 
     pub fn len(self) -> f32 {
-        (self.0 * self.0 + self.1 * self.1).sqrt()
+        math::sqrt(self.0 * self.0 + self.1 * self.1)
     }
 
     pub fn rot90(self) -> Point {
@@ -56,13 +63,19 @@ impl std::ops::Mul<f32> for Point {
         Point(self.0 * rhs, self.1 * rhs)
     }
 }
+impl StateHash for Point {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        hasher.write_f32(self.0);
+        hasher.write_f32(self.1);
+    }
+}
 impl std::fmt::Debug for Point {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "({}, {})", self.0, self.1)
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Line(pub Point, pub Point);
 impl Line {
     pub fn intersects(self, rhs: Line) -> bool {
@@ -100,6 +113,16 @@ impl Line {
         (self.1 - self.0).unit()
     }
 
+    pub fn at_x(self, x: f32) -> Point {
+        let t = (x - self.0 .0) / (self.1 .0 - self.0 .0);
+        self.0 + (self.1 - self.0) * t
+    }
+
+    pub fn at_y(self, y: f32) -> Point {
+        let t = (y - self.0 .1) / (self.1 .1 - self.0 .1);
+        self.0 + (self.1 - self.0) * t
+    }
+
     pub fn projection(self, point: Point) -> Point {
         let direction = self.direction();
         let point = point - self.0;
@@ -107,6 +130,12 @@ impl Line {
         direction * point.dot(direction) + self.0
     }
 
+    pub fn closest(self, point: Point) -> Point {
+        let delta = self.1 - self.0;
+        let t = ((point - self.0).dot(delta) / delta.dot(delta)).clamp(0.0, 1.0);
+        self.0 + delta * t
+    }
+
     fn colinear_intersect(self, rhs: Line) -> bool {
         let t0 = (rhs.0 - self.0).dot_div(self.1).0;
         let t1 = (rhs.1 - self.0).dot_div(self.1).0;
@@ -114,6 +143,12 @@ impl Line {
         (t0 >= 0.0 && t0 <= 1.0) || (t1 >= 0.0 && t1 <= 1.0)
     }
 }
+impl StateHash for Line {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        self.0.hash_state(hasher);
+        self.1.hash_state(hasher);
+    }
+}
 impl std::ops::Add<Point> for Line {
     type Output = Line;
 
@@ -180,8 +215,8 @@ impl Mat3 {
     }
 
     pub fn rotate(angle: f32) -> Mat3 {
-        let s = angle.sin();
-        let c = angle.cos();
+        let s = math::sin(angle);
+        let c = math::cos(angle);
 
         Mat3((c, -s, 0.0), (s, c, 0.0), (0.0, 0.0, 1.0))
     }
@@ -198,6 +233,17 @@ impl Mat3 {
         Mat3((1.0, 0.0, tx), (0.0, 1.0, ty), (0.0, 0.0, 1.0))
     }
 
+    /// Maps the x and y axes onto `x` and `y`, and the origin onto `origin`.
+    pub fn affine(origin: Point, x: Vector, y: Vector) -> Mat3 {
+        Mat3((x.0, y.0, origin.0), (x.1, y.1, origin.1), (0.0, 0.0, 1.0))
+    }
+
+    /// Maps the unit triangle the renderers draw onto `a`, `b` and `c`.
+    pub fn triangle(a: Point, b: Point, c: Point) -> Mat3 {
+        let base = (a + c) * 0.5;
+        Mat3::affine(base, (c - a) * 0.5, b - base)
+    }
+
     pub fn as_f32(&self) -> [f32; 12] {
         [
             self.0 .0, self.1 .0, self.2 .0, 0.0, self.0 .1, self.1 .1, self.2 .1, 0.0, self.0 .2,
@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{game::Game, replay::Replay, score::ScoreRules};
+
+/// 64 bit FNV-1a over little endian bytes. Unlike the std hashers it gives the
+/// same value on every platform and release, so hashes can be compared
+/// between machines.
+#[derive(Clone, Copy, Debug)]
+pub struct StateHasher(u64);
+impl Default for StateHasher {
+    fn default() -> StateHasher {
+        StateHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+impl StateHasher {
+    pub fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.write(&value.to_le_bytes());
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.write(&[value as u8]);
+    }
+
+    /// Floats are hashed by their bits, so even the last ulp counts.
+    pub fn write_f32(&mut self, value: f32) {
+        self.write_u32(value.to_bits());
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Feeds everything that affects how the simulation goes on into the hasher,
+/// and nothing that is only drawn.
+pub trait StateHash {
+    fn hash_state(&self, hasher: &mut StateHasher);
+}
+
+/// Hashes of a run taken every `interval` ticks.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Trace {
+    pub interval: u64,
+    /// Tick and hash of each sample.
+    pub hashes: Vec<(u64, u64)>,
+}
+impl Trace {
+    pub fn new(interval: u64) -> Trace {
+        Trace {
+            interval: interval.max(1),
+            hashes: Vec::new(),
+        }
+    }
+
+    /// Of a replay played back from scratch.
+    pub fn of(replay: &Replay, rules: &ScoreRules, interval: u64) -> Trace {
+        let mut trace = Trace::new(interval);
+        replay.simulate_with(rules, |game| trace.record(game));
+        trace
+    }
+
+    /// Samples the game if `interval` ticks went by since the last sample, or
+    /// if the game restarted.
+    pub fn record(&mut self, game: &mut Game) {
+        let tick = game.tick();
+        if let Some(&(last, _)) = self.hashes.last() {
+            if last <= tick && tick < last + self.interval {
+                return;
+            }
+        }
+        self.hashes.push((tick, game.state_hash()));
+    }
+
+    /// Tick of the first sample where the runs disagree, or `None` if they
+    /// match for as long as both were sampled.
+    pub fn first_divergence(&self, other: &Trace) -> Option<u64> {
+        self.hashes
+            .iter()
+            .zip(&other.hashes)
+            .find(|(ours, theirs)| ours != theirs)
+            .map(|((tick, _), (other_tick, _))| *tick.min(other_tick))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{render::scene::Drawable, ship::Throttle};
+
+    fn replay() -> Replay {
+        let mut game = Game::with_seed(11);
+        game.control(Throttle::Bottom, 1.0);
+        (0..Game::countdown() + 10).for_each(|_| {
+            game.advance();
+        });
+        game.control(Throttle::Bottom, 1.0);
+        game.control(Throttle::Right, 1.0);
+        (0..200).for_each(|_| {
+            // Flames flicker from a thread local generator when drawn, which
+            // must not leak into the physics.
+            let ship = game.ship().clone();
+            let mut flames = ship.active_throttles();
+            flames.triangles().for_each(drop);
+            game.advance();
+        });
+        game.replay().unwrap()
+    }
+
+    #[test]
+    fn same_inputs_hash_the_same() {
+        let replay = replay();
+        let rules = ScoreRules::default();
+        let trace = Trace::of(&replay, &rules, 10);
+        assert_eq!(trace.hashes.len() as u64, replay.ticks / 10 + 1);
+        assert_eq!(trace, Trace::of(&replay, &rules, 10));
+
+        let mut game = replay.simulate(&rules);
+        let hash = game.state_hash();
+        game.ship().active_throttles().triangles().for_each(drop);
+        assert_eq!(game.state_hash(), hash);
+        assert_eq!(trace.hashes.last().unwrap().1, hash);
+    }
+
+    #[test]
+    fn reports_the_first_diverging_tick() {
+        let replay = replay();
+        let rules = ScoreRules::default();
+        let mut changed = replay.clone();
+        let input = changed.inputs.last_mut().unwrap();
+        input.tick += 5;
+        let tick = input.tick;
+
+        let ours = Trace::of(&replay, &rules, 1);
+        let theirs = Trace::of(&changed, &rules, 1);
+        assert_eq!(ours.first_divergence(&theirs), Some(tick - 4));
+        assert_eq!(ours.first_divergence(&ours), None);
+    }
+}
@@ -0,0 +1,293 @@
+use std::{collections::HashMap, fs, io, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{game::Game, replay::Replay, score::ScoreRules};
+
+pub trait Storage {
+    fn read(&self, key: &str) -> Option<String>;
+    fn write(&mut self, key: &str, value: &str) -> io::Result<()>;
+}
+
+#[derive(Default)]
+pub struct MemoryStorage(HashMap<String, String>);
+impl Storage for MemoryStorage {
+    fn read(&self, key: &str) -> Option<String> {
+        self.0.get(key).cloned()
+    }
+
+    fn write(&mut self, key: &str, value: &str) -> io::Result<()> {
+        self.0.insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+}
+
+/// Keeps each key as a JSON file inside `dir`.
+pub struct FileStorage {
+    dir: PathBuf,
+}
+impl FileStorage {
+    pub fn new(dir: PathBuf) -> FileStorage {
+        FileStorage { dir }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+impl Storage for FileStorage {
+    fn read(&self, key: &str) -> Option<String> {
+        fs::read_to_string(self.path(key)).ok()
+    }
+
+    fn write(&mut self, key: &str, value: &str) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.path(key), value)
+    }
+}
+
+#[cfg(feature = "webgl")]
+pub struct LocalStorage(web_sys::Storage);
+#[cfg(feature = "webgl")]
+impl LocalStorage {
+    pub fn new() -> Option<LocalStorage> {
+        let storage = web_sys::window()?.local_storage().ok()??;
+        Some(LocalStorage(storage))
+    }
+}
+#[cfg(feature = "webgl")]
+impl Storage for LocalStorage {
+    fn read(&self, key: &str) -> Option<String> {
+        self.0.get_item(key).ok()?
+    }
+
+    fn write(&mut self, key: &str, value: &str) -> io::Result<()> {
+        self.0
+            .set_item(key, value)
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, format!("{:?}", err)))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub score: u32,
+    pub seed: u64,
+    /// Seconds since the unix epoch.
+    pub date: u64,
+    pub replay: Replay,
+}
+impl Entry {
+    pub fn from_game(game: &Game, date: u64) -> Option<Entry> {
+        let replay = game.replay()?;
+        Some(Entry {
+            score: game.run().total(),
+            seed: replay.seed,
+            date,
+            replay,
+        })
+    }
+
+    /// Whether the replay reproduces the claimed score flying one of the
+    /// built in crafts, so made up ones cannot set records. Replays of older
+    /// versions flew differently and never verify, and ones claiming more
+    /// than `Replay::max_ticks` are refused without being flown.
+    pub fn verify(&self, rules: &ScoreRules) -> bool {
+        self.replay.seed == self.seed
+            && self.replay.is_current()
+            && self.replay.craft.is_builtin()
+            && self.replay.ticks <= Replay::max_ticks()
+            && self.replay.simulate(rules).run().total() == self.score
+    }
+}
+
+pub struct HighScores<S: Storage> {
+    storage: S,
+    entries: Vec<Entry>,
+}
+impl<S: Storage> HighScores<S> {
+    /// Reads the stored table, dropping any entry whose replay does not
+    /// reproduce its score under `rules`.
+    pub fn load(storage: S, rules: &ScoreRules) -> HighScores<S> {
+        let mut entries: Vec<Entry> = storage
+            .read(Self::key())
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        entries.retain(|entry| entry.verify(rules));
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.score));
+        entries.truncate(Self::capacity());
+        HighScores { storage, entries }
+    }
+
+    pub fn capacity() -> usize {
+        10
+    }
+
+    fn key() -> &'static str {
+        "lander-highscores"
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Starts `game` over on the seed of the best stored run, flying against
+    /// the stored runs as ghosts. Games without a seed, like caves, are left
+    /// as they are.
+    pub fn load_ghosts(&self, game: &mut Game) {
+        let best = match self.entries.first() {
+            Some(best) if game.seed().is_some() => best,
+            _ => return,
+        };
+        let rules = std::mem::take(&mut game.rules);
+        *game = Game::with_seed(best.seed);
+        game.rules = rules;
+        for entry in &self.entries {
+            game.add_ghost(entry.score, entry.replay.clone());
+        }
+    }
+
+    pub fn qualifies(&self, score: u32) -> bool {
+        score > 0
+            && (self.entries.len() < Self::capacity()
+                || self.entries.iter().any(|entry| entry.score < score))
+    }
+
+    /// Inserts the entry if it makes the table and its replay reproduces the
+    /// claimed score, returning its rank.
+    pub fn submit(&mut self, entry: Entry, rules: &ScoreRules) -> Option<usize> {
+        if !self.qualifies(entry.score) || !entry.verify(rules) {
+            return None;
+        }
+
+        let rank = self
+            .entries
+            .partition_point(|other| other.score >= entry.score);
+        self.entries.insert(rank, entry);
+        self.entries.truncate(Self::capacity());
+
+        let json = serde_json::to_string(&self.entries).unwrap();
+        if let Err(err) = self.storage.write(Self::key(), &json) {
+            log::warn!("Could not save high scores: {}", err);
+        }
+        Some(rank)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{game::State, ship::Throttle};
+
+    fn landing() -> Game {
+        let mut game = Game::with_seed(3);
+        game.control(Throttle::Bottom, 1.0);
+        (0..Game::countdown()).for_each(|_| {
+            game.advance();
+        });
+        game.control(Throttle::Bottom, 1.0);
+        (0..50).for_each(|_| {
+            game.advance();
+        });
+        game.control(Throttle::Bottom, 0.0);
+        (0..1000).for_each(|_| {
+            game.advance();
+        });
+        assert_eq!(game.state(), State::Landed);
+        game
+    }
+
+    fn entry(score: u32) -> Entry {
+        Entry {
+            score,
+            seed: 0,
+            date: 0,
+            replay: Replay::new(0),
+        }
+    }
+
+    #[test]
+    fn only_verified_scores_are_kept() {
+        let mut scores = HighScores::load(MemoryStorage::default(), &ScoreRules::default());
+        let real = Entry::from_game(&landing(), 1).unwrap();
+        assert!(real.score > 0);
+
+        let mut forged = real.clone();
+        forged.score += 1;
+        assert_eq!(scores.submit(forged, &ScoreRules::default()), None);
+
+        let mut souped_up = real.clone();
+        souped_up.replay.craft.fuel = 1e6;
+        souped_up.replay.craft.thrusters[1].power = 100.0;
+        souped_up.score = souped_up
+            .replay
+            .simulate(&ScoreRules::default())
+            .run()
+            .total();
+        assert_eq!(scores.submit(souped_up, &ScoreRules::default()), None);
+
+        scores.entries.push(entry(real.score + 10));
+        assert_eq!(scores.submit(real.clone(), &ScoreRules::default()), Some(1));
+        assert_eq!(scores.entries()[1], real);
+    }
+
+    #[test]
+    fn table_survives_reload_and_stays_bounded() {
+        let capacity = HighScores::<MemoryStorage>::capacity() as u32;
+        let mut scores = HighScores::load(MemoryStorage::default(), &ScoreRules::default());
+        scores.entries = (1..=capacity).rev().map(entry).collect();
+        assert!(!scores.qualifies(0));
+        assert!(!scores.qualifies(1));
+        assert!(scores.qualifies(2));
+
+        let real = Entry::from_game(&landing(), 5).unwrap();
+        assert_eq!(scores.submit(real.clone(), &ScoreRules::default()), Some(0));
+        assert_eq!(scores.entries().len(), capacity as usize);
+
+        let stored = scores.storage.read(HighScores::<MemoryStorage>::key());
+        assert_eq!(
+            serde_json::from_str::<Vec<Entry>>(&stored.unwrap()).unwrap(),
+            scores.entries
+        );
+        assert_eq!(scores.entries().last().unwrap().score, 2);
+
+        // Only the real entry was flown; the others are dropped on reload.
+        let reloaded = HighScores::load(scores.storage, &ScoreRules::default());
+        assert_eq!(reloaded.entries(), &[real]);
+    }
+
+    #[test]
+    fn stored_runs_fly_as_ghosts_on_their_seed() {
+        let mut scores = HighScores::load(MemoryStorage::default(), &ScoreRules::default());
+        let real = Entry::from_game(&landing(), 1).unwrap();
+        scores.submit(real.clone(), &ScoreRules::default()).unwrap();
+
+        let mut game = Game::with_seed(real.seed + 1);
+        scores.load_ghosts(&mut game);
+        assert_eq!(game.seed(), Some(real.seed));
+        assert_eq!(game.ghosts().count(), 1);
+
+        let mut cave = Game::new(Box::new(crate::terrain::cave::Cave::level(1)));
+        scores.load_ghosts(&mut cave);
+        assert_eq!(cave.seed(), None);
+        assert_eq!(cave.ghosts().count(), 0);
+    }
+
+    #[test]
+    fn stored_entries_are_verified_on_load() {
+        let real = Entry::from_game(&landing(), 1).unwrap();
+        let mut forged = real.clone();
+        forged.score *= 10;
+        let mut outdated = real.clone();
+        outdated.replay.version = 0;
+        let mut endless = real.clone();
+        endless.replay.ticks = u64::MAX;
+
+        let mut storage = MemoryStorage::default();
+        let json = serde_json::to_string(&vec![forged, real.clone(), outdated, endless]).unwrap();
+        storage
+            .write(HighScores::<MemoryStorage>::key(), &json)
+            .unwrap();
+        let scores = HighScores::load(storage, &ScoreRules::default());
+        assert_eq!(scores.entries(), &[real]);
+    }
+}
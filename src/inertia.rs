@@ -1,5 +1,9 @@
-use crate::geom::{Point, Vector};
+use crate::{
+    geom::{Point, Vector},
+    hash::{StateHash, StateHasher},
+};
 
+#[derive(Clone, Copy)]
 pub struct Inertia {
     pub position: Point,
     pub prev: Point,
@@ -20,6 +24,15 @@ impl Inertia {
         self.position = self.position + inertia
     }
 
+    pub fn interpolate(&self, alpha: f32) -> Inertia {
+        let inertia = self.inertia();
+        let position = self.prev + inertia * alpha;
+        Inertia {
+            position,
+            prev: position - inertia,
+        }
+    }
+
     pub fn inertia(&self) -> Point {
         self.position - self.prev
     }
@@ -28,6 +41,12 @@ impl Inertia {
         self.prev = self.prev - (force * Self::step());
     }
 }
+impl StateHash for Inertia {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        self.position.hash_state(hasher);
+        self.prev.hash_state(hasher);
+    }
+}
 impl std::fmt::Debug for Inertia {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}->{:?}", self.position, self.inertia())
@@ -0,0 +1,526 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{game::ShipId, highscore::Storage, ship::Throttle};
+
+/// Gamepad buttons, named after their position on a standard layout.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Button {
+    South,
+    East,
+    West,
+    North,
+    LeftTrigger,
+    RightTrigger,
+    LeftTrigger2,
+    RightTrigger2,
+    Select,
+    Start,
+    LeftThumb,
+    RightThumb,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    Mode,
+}
+impl Button {
+    /// Button at `index` of the web Gamepad API standard mapping.
+    pub fn from_standard_index(index: u32) -> Option<Button> {
+        use Button::*;
+
+        let buttons = [
+            South,
+            East,
+            West,
+            North,
+            LeftTrigger,
+            RightTrigger,
+            LeftTrigger2,
+            RightTrigger2,
+            Select,
+            Start,
+            LeftThumb,
+            RightThumb,
+            DPadUp,
+            DPadDown,
+            DPadLeft,
+            DPadRight,
+            Mode,
+        ];
+        buttons.get(index as usize).copied()
+    }
+}
+
+/// Analog sticks, right and up being positive.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Axis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+}
+impl Axis {
+    /// Axis at `index` of the web Gamepad API standard mapping, which has
+    /// down positive, along with the factor that turns it up.
+    pub fn from_standard_index(index: u32) -> Option<(Axis, f32)> {
+        match index {
+            0 => Some((Axis::LeftStickX, 1.0)),
+            1 => Some((Axis::LeftStickY, -1.0)),
+            2 => Some((Axis::RightStickX, 1.0)),
+            3 => Some((Axis::RightStickY, -1.0)),
+            _ => None,
+        }
+    }
+}
+
+/// Part of the screen, in fractions of its size from the top left corner.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Zone {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+impl Zone {
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.left && x < self.right && y >= self.top && y < self.bottom
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Source {
+    /// A key by its web `KeyboardEvent.code`, such as `ArrowUp` or `KeyW`.
+    Key(String),
+    Button(Button),
+    /// One direction of an axis: `1.0` for right or up, `-1.0` for the other.
+    Axis(Axis, f32),
+    Touch(Zone),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Binding {
+    pub source: Source,
+    #[serde(default)]
+    pub ship: ShipId,
+    pub throttle: Throttle,
+}
+
+/// Something the player did on a device.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    Key {
+        code: String,
+        pressed: bool,
+    },
+    /// `value` goes from 0 to 1, analog triggers reporting anything between.
+    /// `pad` tells gamepads apart, so each keeps its own buttons.
+    Button {
+        pad: u32,
+        button: Button,
+        value: f32,
+    },
+    Axis {
+        pad: u32,
+        axis: Axis,
+        value: f32,
+    },
+    /// Position in fractions of the screen size. A finger keeps its `id` from
+    /// the moment it touches the screen until it is lifted, and holds only
+    /// the zone it is currently over.
+    Touch {
+        id: u32,
+        x: f32,
+        y: f32,
+        pressed: bool,
+    },
+}
+
+/// A thruster changing how hard it is pushed, `intensity` 0 letting go of
+/// it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Action {
+    pub ship: ShipId,
+    pub throttle: Throttle,
+    pub intensity: f32,
+}
+impl Action {
+    pub fn active(&self) -> bool {
+        self.intensity > 0.0
+    }
+}
+
+/// Turns device events into thruster actions through a table of bindings.
+/// Each thruster follows the strongest of the sources bound to it, so letting
+/// go of a key does not cut a trigger that is still held.
+pub struct InputMap {
+    bindings: Vec<Binding>,
+    /// How hard each binding is pushed by each gamepad or finger holding it.
+    levels: Vec<HashMap<u32, f32>>,
+    intensities: HashMap<(ShipId, Throttle), f32>,
+    pub dead_zone: f32,
+}
+impl Default for InputMap {
+    fn default() -> InputMap {
+        let player = ShipId::PLAYER;
+        let key = |code: &str| Source::Key(code.to_string());
+        let zone = |left, top, right, bottom| {
+            Source::Touch(Zone {
+                left,
+                top,
+                right,
+                bottom,
+            })
+        };
+
+        let bindings = vec![
+            (key("ArrowLeft"), player, Throttle::Left),
+            (key("ArrowUp"), player, Throttle::Bottom),
+            (key("ArrowRight"), player, Throttle::Right),
+            (Source::Button(Button::LeftTrigger), player, Throttle::Left),
+            (Source::Button(Button::DPadLeft), player, Throttle::Left),
+            (Source::Axis(Axis::LeftStickX, -1.0), player, Throttle::Left),
+            (
+                Source::Button(Button::RightTrigger2),
+                player,
+                Throttle::Bottom,
+            ),
+            (Source::Button(Button::South), player, Throttle::Bottom),
+            (
+                Source::Button(Button::RightTrigger),
+                player,
+                Throttle::Right,
+            ),
+            (Source::Button(Button::DPadRight), player, Throttle::Right),
+            (Source::Axis(Axis::LeftStickX, 1.0), player, Throttle::Right),
+            (zone(0.0, 0.0, 1.0, 0.3), player, Throttle::Bottom),
+            (zone(0.0, 0.3, 0.5, 1.0), player, Throttle::Left),
+            (zone(0.5, 0.3, 1.0, 1.0), player, Throttle::Right),
+        ];
+        InputMap::new(
+            bindings
+                .into_iter()
+                .map(|(source, ship, throttle)| Binding {
+                    source,
+                    ship,
+                    throttle,
+                })
+                .collect(),
+        )
+    }
+}
+impl InputMap {
+    pub fn new(bindings: Vec<Binding>) -> InputMap {
+        InputMap {
+            levels: vec![HashMap::new(); bindings.len()],
+            bindings,
+            intensities: HashMap::new(),
+            dead_zone: 0.2,
+        }
+    }
+
+    fn key() -> &'static str {
+        "lander-bindings"
+    }
+
+    /// Bindings saved in `storage`, or the default ones.
+    pub fn load<S: Storage>(storage: &S) -> InputMap {
+        storage
+            .read(Self::key())
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .map(InputMap::new)
+            .unwrap_or_default()
+    }
+
+    pub fn save<S: Storage>(&self, storage: &mut S) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(&self.bindings).unwrap();
+        storage.write(Self::key(), &json)
+    }
+
+    pub fn bindings(&self) -> &[Binding] {
+        &self.bindings
+    }
+
+    pub fn bind(&mut self, source: Source, ship: ShipId, throttle: Throttle) {
+        self.bindings.push(Binding {
+            source,
+            ship,
+            throttle,
+        });
+        self.levels.push(HashMap::new());
+    }
+
+    /// Binds A, W and D to `ship` for a second player on the keyboard, unless
+    /// those keys already steer a ship.
+    pub fn bind_second_player(&mut self, ship: ShipId) {
+        let keys = [
+            ("KeyA", Throttle::Left),
+            ("KeyW", Throttle::Bottom),
+            ("KeyD", Throttle::Right),
+        ]
+        .map(|(code, throttle)| (Source::Key(code.to_string()), throttle));
+        let taken = self
+            .bindings
+            .iter()
+            .any(|binding| keys.iter().any(|(source, _)| &binding.source == source));
+        if !taken {
+            for (source, throttle) in keys {
+                self.bind(source, ship, throttle);
+            }
+        }
+    }
+
+    /// Removes every binding of `source`, returning whether there was any.
+    pub fn unbind(&mut self, source: &Source) -> bool {
+        let before = self.bindings.len();
+        let mut index = 0;
+        while index < self.bindings.len() {
+            if &self.bindings[index].source == source {
+                self.bindings.remove(index);
+                self.levels.remove(index);
+            } else {
+                index += 1;
+            }
+        }
+        self.bindings.len() != before
+    }
+
+    /// Actions for every thruster whose intensity the event changed.
+    pub fn handle(&mut self, event: &Event) -> Vec<Action> {
+        let dead_zone = self.dead_zone;
+        for (binding, level) in self.bindings.iter().zip(self.levels.iter_mut()) {
+            let (device, value) = match (&binding.source, event) {
+                (Source::Key(bound), Event::Key { code, pressed }) if bound == code => {
+                    (0, *pressed as u8 as f32)
+                }
+                (Source::Button(bound), Event::Button { pad, button, value })
+                    if bound == button =>
+                {
+                    (*pad, *value)
+                }
+                (Source::Axis(bound, sign), Event::Axis { pad, axis, value }) if bound == axis => {
+                    (*pad, value * sign)
+                }
+                // Every zone hears about every finger, so one that slides
+                // off or is lifted elsewhere lets go of where it was.
+                (Source::Touch(zone), Event::Touch { id, x, y, pressed }) => {
+                    (*id, (*pressed && zone.contains(*x, *y)) as u8 as f32)
+                }
+                _ => continue,
+            };
+            // Sixteenths are plenty, and keep a noisy stick from flooding
+            // replays and the network with inputs.
+            if value < dead_zone {
+                level.remove(&device);
+            } else {
+                level.insert(device, (value.min(1.0) * 16.0).round() / 16.0);
+            }
+        }
+
+        let mut targets: Vec<(ShipId, Throttle)> = Vec::new();
+        let mut strongest: HashMap<(ShipId, Throttle), f32> = HashMap::new();
+        for (binding, levels) in self.bindings.iter().zip(self.levels.iter()) {
+            let level = levels.values().fold(0.0, |a: f32, &b| a.max(b));
+            let target = (binding.ship, binding.throttle);
+            let intensity = strongest.entry(target).or_insert_with(|| {
+                targets.push(target);
+                0.0
+            });
+            *intensity = intensity.max(level);
+        }
+
+        let mut actions = Vec::new();
+        for (ship, throttle) in targets {
+            let intensity = strongest[&(ship, throttle)];
+            let previous = self.intensities.insert((ship, throttle), intensity);
+            if previous.unwrap_or(0.0) != intensity {
+                actions.push(Action {
+                    ship,
+                    throttle,
+                    intensity,
+                });
+            }
+        }
+        actions
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::highscore::MemoryStorage;
+
+    fn key(code: &str, pressed: bool) -> Event {
+        Event::Key {
+            code: code.to_string(),
+            pressed,
+        }
+    }
+
+    fn action(ship: ShipId, throttle: Throttle, intensity: f32) -> Action {
+        Action {
+            ship,
+            throttle,
+            intensity,
+        }
+    }
+
+    #[test]
+    fn keys_drive_their_ships() {
+        let mut input = InputMap::default();
+        let player = ShipId::PLAYER;
+        assert_eq!(
+            input.handle(&key("ArrowUp", true)),
+            vec![action(player, Throttle::Bottom, 1.0)]
+        );
+        assert_eq!(input.handle(&key("ArrowUp", true)), vec![]);
+        assert_eq!(input.handle(&key("KeyA", true)), vec![]);
+        input.bind_second_player(ShipId(1));
+        input.bind_second_player(ShipId(2));
+        assert_eq!(
+            input.handle(&key("KeyA", true)),
+            vec![action(ShipId(1), Throttle::Left, 1.0)]
+        );
+        assert_eq!(
+            input.handle(&key("ArrowUp", false)),
+            vec![action(player, Throttle::Bottom, 0.0)]
+        );
+        assert_eq!(input.handle(&key("Escape", true)), vec![]);
+    }
+
+    #[test]
+    fn strongest_source_wins() {
+        let mut input = InputMap::default();
+        let trigger = |value| Event::Button {
+            pad: 0,
+            button: Button::RightTrigger2,
+            value,
+        };
+        assert_eq!(input.handle(&trigger(0.1)), vec![]);
+        let actions = input.handle(&trigger(0.6));
+        assert_eq!(
+            actions,
+            vec![action(ShipId::PLAYER, Throttle::Bottom, 0.625)]
+        );
+        assert!(actions[0].active());
+        assert_eq!(input.handle(&trigger(0.61)), vec![]);
+
+        input.handle(&key("ArrowUp", true));
+        assert_eq!(input.handle(&trigger(0.75)), vec![]);
+        assert_eq!(
+            input.handle(&key("ArrowUp", false)),
+            vec![action(ShipId::PLAYER, Throttle::Bottom, 0.75)]
+        );
+
+        let stick = |value| Event::Axis {
+            pad: 0,
+            axis: Axis::LeftStickX,
+            value,
+        };
+        assert_eq!(
+            input.handle(&stick(-1.0)),
+            vec![action(ShipId::PLAYER, Throttle::Left, 1.0)]
+        );
+        assert_eq!(
+            input.handle(&stick(0.5)),
+            vec![
+                action(ShipId::PLAYER, Throttle::Left, 0.0),
+                action(ShipId::PLAYER, Throttle::Right, 0.5),
+            ]
+        );
+    }
+
+    #[test]
+    fn pads_keep_their_own_buttons() {
+        let mut input = InputMap::default();
+        let trigger = |pad, value| Event::Button {
+            pad,
+            button: Button::RightTrigger2,
+            value,
+        };
+        assert_eq!(
+            input.handle(&trigger(0, 1.0)),
+            vec![action(ShipId::PLAYER, Throttle::Bottom, 1.0)]
+        );
+        assert_eq!(input.handle(&trigger(1, 0.0)), vec![]);
+        assert_eq!(input.handle(&trigger(1, 0.5)), vec![]);
+        assert_eq!(
+            input.handle(&trigger(0, 0.0)),
+            vec![action(ShipId::PLAYER, Throttle::Bottom, 0.5)]
+        );
+
+        let stick = |pad, value| Event::Axis {
+            pad,
+            axis: Axis::LeftStickX,
+            value,
+        };
+        input.handle(&stick(0, -1.0));
+        assert_eq!(input.handle(&stick(1, 0.0)), vec![]);
+    }
+
+    fn touch(id: u32, x: f32, y: f32, pressed: bool) -> Event {
+        Event::Touch { id, x, y, pressed }
+    }
+
+    #[test]
+    fn touch_zones_split_the_screen() {
+        let mut input = InputMap::default();
+        assert_eq!(
+            input.handle(&touch(0, 0.5, 0.1, true)),
+            vec![action(ShipId::PLAYER, Throttle::Bottom, 1.0)]
+        );
+        assert_eq!(
+            input.handle(&touch(1, 0.8, 0.9, true)),
+            vec![action(ShipId::PLAYER, Throttle::Right, 1.0)]
+        );
+    }
+
+    #[test]
+    fn fingers_let_go_wherever_they_are_lifted() {
+        let mut input = InputMap::default();
+        let player = ShipId::PLAYER;
+        input.handle(&touch(0, 0.2, 0.9, true));
+        assert_eq!(
+            input.handle(&touch(0, 0.8, 0.9, true)),
+            vec![
+                action(player, Throttle::Left, 0.0),
+                action(player, Throttle::Right, 1.0),
+            ]
+        );
+        assert_eq!(
+            input.handle(&touch(0, 2.0, 2.0, false)),
+            vec![action(player, Throttle::Right, 0.0)]
+        );
+
+        input.handle(&touch(1, 0.6, 0.5, true));
+        input.handle(&touch(2, 0.9, 0.5, true));
+        assert_eq!(input.handle(&touch(1, 0.6, 0.5, false)), vec![]);
+        assert_eq!(
+            input.handle(&touch(2, 0.9, 0.5, false)),
+            vec![action(player, Throttle::Right, 0.0)]
+        );
+    }
+
+    #[test]
+    fn rebinding_is_saved() {
+        let mut input = InputMap::default();
+        let space = Source::Key("Space".to_string());
+        assert!(input.unbind(&Source::Key("ArrowUp".to_string())));
+        input.bind(space, ShipId::PLAYER, Throttle::Bottom);
+        assert_eq!(input.handle(&key("ArrowUp", true)), vec![]);
+
+        let mut storage = MemoryStorage::default();
+        assert_eq!(
+            InputMap::load(&storage).bindings(),
+            InputMap::default().bindings()
+        );
+        input.save(&mut storage).unwrap();
+        let mut loaded = InputMap::load(&storage);
+        assert_eq!(loaded.bindings(), input.bindings());
+        assert_eq!(
+            loaded.handle(&key("Space", true)),
+            vec![action(ShipId::PLAYER, Throttle::Bottom, 1.0)]
+        );
+    }
+}
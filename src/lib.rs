@@ -1,9 +1,21 @@
 #![feature(generic_associated_types, type_alias_impl_trait)]
 
+pub mod camera;
+pub mod constraint;
+pub mod game;
 pub mod geom;
+pub mod hash;
+pub mod highscore;
 pub mod inertia;
+pub mod input;
+pub mod math;
+pub mod net;
 pub mod render;
+pub mod replay;
+pub mod score;
 pub mod ship;
+pub mod sling;
+pub mod terrain;
 pub mod world;
 
 #[cfg(feature = "webgl")]
@@ -18,14 +30,37 @@ pub fn load() {
 
 #[cfg(feature = "webgl")]
 #[wasm_bindgen]
-pub struct World(world::World<render::webgl::target::WebglRenderTarget>);
+pub struct World(
+    world::World<render::webgl::target::WebglRenderTarget>,
+    Option<highscore::HighScores<highscore::LocalStorage>>,
+);
 #[cfg(feature = "webgl")]
 #[wasm_bindgen]
 impl World {
     #[wasm_bindgen(constructor)]
     pub fn new(canvas: web_sys::HtmlCanvasElement) -> World {
         let target = render::webgl::target::WebglRenderTarget::new(canvas);
-        World(target.into())
+        World::with_world(target.into())
+    }
+
+    /// A world flying through a cave instead of over open land.
+    pub fn cave(canvas: web_sys::HtmlCanvasElement, seed: u32) -> World {
+        let target = render::webgl::target::WebglRenderTarget::new(canvas);
+        let cave = Box::new(terrain::cave::Cave::level(seed.into()));
+        World::with_world(world::World::with_terrain(target, cave))
+    }
+
+    fn with_world(mut world: world::World<render::webgl::target::WebglRenderTarget>) -> World {
+        if let Some(storage) = highscore::LocalStorage::new() {
+            *world.input_map() = input::InputMap::load(&storage);
+        }
+        let rules = &world.game().rules;
+        let high_scores = highscore::LocalStorage::new()
+            .map(|storage| highscore::HighScores::load(storage, rules));
+        if let Some(high_scores) = &high_scores {
+            high_scores.load_ghosts(world.game());
+        }
+        World(world, high_scores)
     }
 
     pub fn resize(&mut self, width: u32, height: u32) {
@@ -36,7 +71,158 @@ impl World {
         self.0.redraw()
     }
 
-    pub fn control(&mut self, throttle: ship::Throttle, activate: bool) {
-        self.0.control(throttle, activate);
+    pub fn control(&mut self, throttle: ship::Throttle, level: f32) {
+        self.0.control(throttle, level);
+    }
+
+    /// `code` is the `KeyboardEvent.code` of the key.
+    pub fn key(&mut self, code: String, pressed: bool) {
+        self.0.input(&input::Event::Key { code, pressed });
+    }
+
+    /// `id` is the `Touch.identifier`, and `x` and `y` are fractions of the
+    /// canvas size. Moving fingers are reported as still pressed.
+    pub fn touch(&mut self, id: u32, x: f32, y: f32, pressed: bool) {
+        self.0.input(&input::Event::Touch { id, x, y, pressed });
+    }
+
+    /// `pad` is the `Gamepad.index`, and `index` follows the standard
+    /// Gamepad API mapping.
+    pub fn gamepad_button(&mut self, pad: u32, index: u32, value: f32) {
+        if let Some(button) = input::Button::from_standard_index(index) {
+            self.0.input(&input::Event::Button { pad, button, value });
+        }
+    }
+
+    pub fn gamepad_axis(&mut self, pad: u32, index: u32, value: f32) {
+        if let Some((axis, sign)) = input::Axis::from_standard_index(index) {
+            let value = value * sign;
+            self.0.input(&input::Event::Axis { pad, axis, value });
+        }
+    }
+
+    pub fn add_ship(&mut self) -> u32 {
+        self.0.add_ship().0
+    }
+
+    pub fn remove_ship(&mut self, ship: u32) -> bool {
+        self.0.remove_ship(game::ShipId(ship))
+    }
+
+    pub fn control_ship(&mut self, ship: u32, throttle: ship::Throttle, level: f32) {
+        self.0.control_ship(game::ShipId(ship), throttle, level);
+    }
+
+    pub fn add_view(&mut self, ship: u32) {
+        self.0.add_view(game::ShipId(ship));
+    }
+
+    pub fn set_collisions(&mut self, collisions: bool) {
+        self.0.game().set_collisions(collisions);
+    }
+
+    /// Lets thrusters misfire and break down.
+    pub fn set_hard(&mut self, hard: bool) {
+        let thrusters = if hard {
+            ship::ThrusterModel::hard()
+        } else {
+            ship::ThrusterModel::default()
+        };
+        self.0.game().set_thrusters(thrusters);
+    }
+
+    /// Flies one of the built in crafts, by name.
+    pub fn set_craft(&mut self, name: &str) -> bool {
+        match ship::definition::ShipDefinition::named(name) {
+            Some(craft) => {
+                self.0.game().set_craft(craft);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Sends ships out to sling cargo onto far pads instead of just landing.
+    pub fn set_delivery(&mut self, delivery: bool) {
+        let mission = if delivery {
+            game::Mission::Delivery
+        } else {
+            game::Mission::Landing
+        };
+        self.0.game().set_mission(mission);
+    }
+
+    pub fn state(&self) -> game::State {
+        self.0.state()
+    }
+
+    pub fn poll_event(&mut self) -> Option<game::State> {
+        let state = self.0.poll_event()?;
+        if state == game::State::GameOver {
+            self.submit_score();
+        }
+        Some(state)
+    }
+
+    pub fn high_scores(&self) -> Vec<u32> {
+        self.1
+            .iter()
+            .flat_map(|scores| scores.entries())
+            .map(|entry| entry.score)
+            .collect()
+    }
+
+    pub fn score(&mut self) -> u32 {
+        self.0.game().run().total()
+    }
+
+    pub fn last_score(&mut self) -> u32 {
+        self.0
+            .game()
+            .last_landing()
+            .map(|landing| landing.total)
+            .unwrap_or(0)
+    }
+
+    pub fn lives(&mut self) -> u32 {
+        self.0.game().lives()
+    }
+
+    pub fn fuel(&mut self) -> f32 {
+        self.0.game().ship().fuel()
+    }
+
+    /// The local ship's telemetry as JSON.
+    pub fn telemetry(&self) -> Option<String> {
+        let telemetry = self.0.telemetry()?;
+        Some(serde_json::to_string(&telemetry).unwrap())
+    }
+
+    pub fn pause(&mut self) {
+        self.0.pause();
+    }
+
+    pub fn resume(&mut self) {
+        self.0.resume();
+    }
+
+    pub fn set_time_scale(&mut self, scale: f32) {
+        self.0.set_time_scale(scale);
+    }
+
+    pub fn dropped_time(&self) -> f64 {
+        self.0.dropped_time().as_secs_f64()
+    }
+}
+
+#[cfg(feature = "webgl")]
+impl World {
+    fn submit_score(&mut self) {
+        let date = (js_sys::Date::now() / 1000.0) as u64;
+        let game = self.0.game();
+        if let (Some(scores), Some(entry)) = (&mut self.1, highscore::Entry::from_game(game, date))
+        {
+            scores.submit(entry, &game.rules);
+        }
     }
 }
@@ -0,0 +1,111 @@
+//! Floating point functions the simulation relies on. The standard library
+//! leaves transcendental functions to the platform, so with the
+//! `deterministic` feature they come from a software implementation instead
+//! and every target computes the same bits. Plain arithmetic and `sqrt` are
+//! already exact under IEEE 754, but go through here too so the whole
+//! simulation has a single source.
+
+#[cfg(feature = "deterministic")]
+pub fn sqrt(x: f32) -> f32 {
+    libm::sqrtf(x)
+}
+
+#[cfg(not(feature = "deterministic"))]
+pub fn sqrt(x: f32) -> f32 {
+    x.sqrt()
+}
+
+#[cfg(feature = "deterministic")]
+pub fn sin(x: f32) -> f32 {
+    libm::sinf(x)
+}
+
+#[cfg(not(feature = "deterministic"))]
+pub fn sin(x: f32) -> f32 {
+    x.sin()
+}
+
+#[cfg(feature = "deterministic")]
+pub fn cos(x: f32) -> f32 {
+    libm::cosf(x)
+}
+
+#[cfg(not(feature = "deterministic"))]
+pub fn cos(x: f32) -> f32 {
+    x.cos()
+}
+
+#[cfg(feature = "deterministic")]
+pub fn atan2(y: f32, x: f32) -> f32 {
+    libm::atan2f(y, x)
+}
+
+#[cfg(not(feature = "deterministic"))]
+pub fn atan2(y: f32, x: f32) -> f32 {
+    y.atan2(x)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn agrees_with_the_platform() {
+        for step in -40..=40 {
+            let x = step as f32 * 0.1;
+            assert!((sin(x) - x.sin()).abs() < 1e-6);
+            assert!((cos(x) - x.cos()).abs() < 1e-6);
+            assert!((atan2(x, 1.5) - x.atan2(1.5)).abs() < 1e-6);
+            assert_eq!(sqrt(x.abs()), x.abs().sqrt());
+        }
+    }
+
+    /// Recorded with the `deterministic` feature. A change in here means
+    /// replays saved by earlier builds no longer play back the same.
+    #[cfg(feature = "deterministic")]
+    #[test]
+    fn matches_the_golden_trajectory() {
+        use crate::{game::Game, hash::Trace, score::ScoreRules, ship::Throttle};
+
+        let mut game = Game::with_seed(2021);
+        let script = [
+            (0, Throttle::Bottom, 1.0),
+            (310, Throttle::Bottom, 1.0),
+            (330, Throttle::Left, 1.0),
+            (360, Throttle::Left, 0.0),
+            (420, Throttle::Right, 1.0),
+            (480, Throttle::Right, 0.0),
+            (600, Throttle::Bottom, 0.0),
+        ];
+        for &(tick, throttle, level) in script.iter() {
+            while game.tick() < tick {
+                game.advance();
+            }
+            game.control(throttle, level);
+        }
+        while game.tick() < 1200 {
+            game.advance();
+        }
+
+        let trace = Trace::of(&game.replay().unwrap(), &ScoreRules::default(), 100);
+        let golden: &[(u64, u64)] = &[
+            (0, 14847020301026883281),
+            (100, 16777822404744162524),
+            (200, 14542360176433743260),
+            (300, 4368013034501198036),
+            (400, 446260672917815654),
+            (500, 14093797714516441903),
+            (600, 10344606329524003484),
+            (700, 18060214500525695407),
+            (800, 5772273911645188173),
+            (900, 15937622473930195495),
+            (1000, 8993997289088209720),
+            (1100, 9365232590585966215),
+            (1200, 15795198210037367343),
+        ];
+        assert_eq!(trace.hashes, golden);
+        let origin = game.ship().origin();
+        assert_eq!((origin.0, origin.1), (302.3056, -103.19786));
+        assert_eq!(game.state(), crate::game::State::Crashed);
+    }
+}
@@ -0,0 +1,548 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game::{Game, ShipId},
+    replay::{Input, Membership, Replay},
+    ship::{Impact, Throttle},
+};
+
+/// What clients and the relay server tell each other. Ticks count from the
+/// start of the networked session and keep growing across restarts.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Message {
+    /// Sent by the relay to a client that just connected, with everything
+    /// relayed so far. `history.ticks` is the step the session reached.
+    Welcome {
+        ship: ShipId,
+        history: Box<Replay>,
+    },
+    /// A control change. The relay overwrites `ship` with the sender's.
+    Input(Input),
+    Membership(Membership),
+    /// Sent by a client once every input it has for ticks before `tick` was
+    /// sent.
+    Ready {
+        tick: u64,
+    },
+    /// Sent by the relay once every input for ticks before `tick` was relayed.
+    Step {
+        tick: u64,
+    },
+    /// Sent by a client every few ticks with its state hash.
+    Hash {
+        tick: u64,
+        hash: u64,
+    },
+    /// Sent by the relay when clients reported different hashes for `tick`.
+    Desync {
+        tick: u64,
+    },
+}
+impl Message {
+    pub fn encode(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn decode(text: &str) -> Option<Message> {
+        serde_json::from_str(text).ok()
+    }
+}
+
+/// A message from the relay, either for a single client or for all of them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Envelope {
+    pub to: Option<ShipId>,
+    pub message: Message,
+}
+impl Envelope {
+    fn broadcast(message: Message) -> Envelope {
+        Envelope { to: None, message }
+    }
+
+    pub fn is_for(&self, ship: ShipId) -> bool {
+        self.to.unwrap_or(ship) == ship
+    }
+}
+
+/// Server side of lockstep play. It gives each client a ship, puts every
+/// input in one order for everybody and lets clients step forward once all
+/// of them promised their inputs up to that tick.
+pub struct Relay {
+    history: Replay,
+    ready: BTreeMap<ShipId, u64>,
+    next_id: u32,
+    step: u64,
+    /// First hash reported for each tick some client has yet to report.
+    hashes: BTreeMap<u64, u64>,
+    /// Last tick each client reported a hash for.
+    hashed: BTreeMap<ShipId, u64>,
+    desync: Option<u64>,
+}
+impl Relay {
+    pub fn new(seed: u64, collisions: bool) -> Relay {
+        let mut history = Replay::new(seed);
+        history.collisions = collisions;
+        Relay {
+            history,
+            ready: BTreeMap::new(),
+            next_id: 0,
+            step: 0,
+            hashes: BTreeMap::new(),
+            hashed: BTreeMap::new(),
+            desync: None,
+        }
+    }
+
+    pub fn step(&self) -> u64 {
+        self.step
+    }
+
+    /// First tick clients disagreed on, if they ever did.
+    pub fn desync(&self) -> Option<u64> {
+        self.desync
+    }
+
+    pub fn history(&self) -> Replay {
+        let mut history = self.history.clone();
+        history.ticks = self.step;
+        history
+    }
+
+    pub fn connect(&mut self) -> (ShipId, Vec<Envelope>) {
+        let ship = ShipId(self.next_id);
+        self.next_id += 1;
+        self.ready.insert(ship, self.step);
+        self.hashed.insert(ship, 0);
+
+        let mut out = vec![Envelope {
+            to: Some(ship),
+            message: Message::Welcome {
+                ship,
+                history: Box::new(self.history()),
+            },
+        }];
+        // The player's ship is always in the game.
+        if ship != ShipId::PLAYER {
+            out.push(self.membership(self.step, ship, true));
+        }
+        (ship, out)
+    }
+
+    pub fn disconnect(&mut self, ship: ShipId) -> Vec<Envelope> {
+        let ready = match self.ready.remove(&ship) {
+            Some(ready) => ready,
+            None => return Vec::new(),
+        };
+        self.hashed.remove(&ship);
+        self.forget_hashes();
+
+        // The client may have simulated up to what it promised, so the ship
+        // leaves only after that.
+        let mut out = Vec::new();
+        if ship != ShipId::PLAYER {
+            out.push(self.membership(ready, ship, false));
+        }
+        out.extend(self.update_step());
+        out
+    }
+
+    pub fn receive(&mut self, ship: ShipId, message: Message) -> Vec<Envelope> {
+        let ready = match self.ready.get_mut(&ship) {
+            Some(ready) => ready,
+            None => return Vec::new(),
+        };
+
+        match message {
+            Message::Input(mut input) => {
+                if input.tick < *ready {
+                    log::warn!(
+                        "Dropping late input from {:?} for tick {}",
+                        ship,
+                        input.tick
+                    );
+                    return Vec::new();
+                }
+                input.ship = ship;
+                let index = self
+                    .history
+                    .inputs
+                    .partition_point(|other| other.tick <= input.tick);
+                self.history.inputs.insert(index, input);
+                vec![Envelope::broadcast(Message::Input(input))]
+            }
+            Message::Ready { tick } => {
+                *ready = (*ready).max(tick);
+                self.update_step().into_iter().collect()
+            }
+            Message::Hash { tick, hash } => {
+                let first = *self.hashes.entry(tick).or_insert(hash);
+                let reported = self.hashed.entry(ship).or_default();
+                *reported = (*reported).max(tick);
+                self.forget_hashes();
+                if first == hash || matches!(self.desync, Some(desync) if desync <= tick) {
+                    return Vec::new();
+                }
+                log::warn!("{:?} went out of sync at tick {}", ship, tick);
+                self.desync = Some(tick);
+                vec![Envelope::broadcast(Message::Desync { tick })]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Drops the hashes of ticks every client has reported on.
+    fn forget_hashes(&mut self) {
+        if let Some(&confirmed) = self.hashed.values().min() {
+            self.hashes = self.hashes.split_off(&(confirmed + 1));
+        }
+    }
+
+    fn membership(&mut self, tick: u64, ship: ShipId, present: bool) -> Envelope {
+        let change = Membership {
+            tick,
+            ship,
+            present,
+        };
+        let index = self
+            .history
+            .roster
+            .partition_point(|other| other.tick <= tick);
+        self.history.roster.insert(index, change);
+        Envelope::broadcast(Message::Membership(change))
+    }
+
+    fn update_step(&mut self) -> Option<Envelope> {
+        let step = *self.ready.values().min()?;
+        if step <= self.step {
+            return None;
+        }
+        self.step = step;
+        Some(Envelope::broadcast(Message::Step { tick: step }))
+    }
+}
+
+/// Client side of lockstep play. Local control changes are sent to the relay
+/// stamped a few ticks ahead and only applied once they come back, so every
+/// client runs the same inputs on the same ticks.
+pub struct Lockstep {
+    ship: ShipId,
+    tick: u64,
+    step: u64,
+    ready: u64,
+    inputs: Vec<Input>,
+    roster: Vec<Membership>,
+    outbox: VecDeque<Message>,
+    hashed: u64,
+    desync: Option<u64>,
+}
+impl Lockstep {
+    /// Starts the session described by the relay's welcome, caught up to the
+    /// step it reached. Sessions of another version cannot be joined.
+    pub fn join(welcome: Message) -> Option<(Lockstep, Game)> {
+        let (ship, history) = match welcome {
+            Message::Welcome { ship, history } if history.is_current() => (ship, history),
+            _ => return None,
+        };
+
+        let mut game = Game::with_seed(history.seed);
+        game.set_collisions(history.collisions);
+        game.set_thrusters(history.thrusters);
+        game.set_craft(history.craft.clone());
+        game.set_mission(history.mission);
+        let mut lockstep = Lockstep {
+            ship,
+            tick: 0,
+            step: history.ticks,
+            ready: history.ticks,
+            inputs: history.inputs,
+            roster: history.roster,
+            outbox: VecDeque::new(),
+            hashed: 0,
+            desync: None,
+        };
+        while !lockstep.stalled() {
+            lockstep.advance(&mut game);
+        }
+        Some((lockstep, game))
+    }
+
+    /// Ticks between a local control change and the tick it is applied on.
+    pub fn delay() -> u64 {
+        10
+    }
+
+    /// Ticks between state hashes sent to the relay.
+    pub fn hash_interval() -> u64 {
+        100
+    }
+
+    pub fn ship(&self) -> ShipId {
+        self.ship
+    }
+
+    /// First tick some client disagreed with the others on, as reported by
+    /// the relay.
+    pub fn desync(&self) -> Option<u64> {
+        self.desync
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Whether the next tick still waits for some client's inputs.
+    pub fn stalled(&self) -> bool {
+        self.tick >= self.step
+    }
+
+    pub fn control(&mut self, throttle: Throttle, level: f32) {
+        let tick = (self.tick + Self::delay()).max(self.ready);
+        self.outbox.push_back(Message::Input(Input {
+            tick,
+            ship: self.ship,
+            throttle,
+            level,
+        }));
+    }
+
+    pub fn receive(&mut self, message: Message) {
+        match message {
+            Message::Input(input) => self.inputs.push(input),
+            Message::Membership(change) => self.roster.push(change),
+            Message::Step { tick } => self.step = self.step.max(tick),
+            Message::Desync { tick } => {
+                self.desync = Some(self.desync.map_or(tick, |desync| desync.min(tick)))
+            }
+            Message::Welcome { .. } | Message::Ready { .. } | Message::Hash { .. } => {}
+        }
+    }
+
+    /// Next message for the relay: queued inputs first, then a promise that
+    /// nothing earlier than the delay is coming.
+    pub fn poll_outgoing(&mut self) -> Option<Message> {
+        if let Some(message) = self.outbox.pop_front() {
+            return Some(message);
+        }
+
+        let ready = self.tick + Self::delay();
+        if ready <= self.ready {
+            return None;
+        }
+        self.ready = ready;
+        Some(Message::Ready { tick: ready })
+    }
+
+    /// Runs the next tick of `game`, unless it is stalled, returning the
+    /// impacts of that tick.
+    pub fn advance(&mut self, game: &mut Game) -> Vec<(ShipId, Impact)> {
+        if self.stalled() {
+            return Vec::new();
+        }
+
+        let tick = self.tick;
+        self.roster.retain(|change| {
+            if change.tick > tick {
+                return true;
+            }
+            if change.present {
+                game.join(change.ship);
+            } else {
+                game.remove_ship(change.ship);
+            }
+            false
+        });
+        self.inputs.retain(|input| {
+            if input.tick > tick {
+                return true;
+            }
+            game.control_ship(input.ship, input.throttle, input.level);
+            false
+        });
+
+        let impacts = game.advance();
+        self.tick += 1;
+        if self.tick == self.hashed + Self::hash_interval() {
+            self.hashed = self.tick;
+            self.outbox.push_back(Message::Hash {
+                tick: self.tick,
+                hash: game.state_hash(),
+            });
+        }
+        impacts
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// A relay and its clients passing messages in memory.
+    struct Network {
+        relay: Relay,
+        clients: BTreeMap<ShipId, (Lockstep, Game)>,
+        queue: Vec<Envelope>,
+    }
+    impl Network {
+        fn connect(&mut self) -> ShipId {
+            let (ship, mut out) = self.relay.connect();
+            let welcome = out.remove(0).message;
+            self.clients.insert(ship, Lockstep::join(welcome).unwrap());
+            self.queue.extend(out);
+            ship
+        }
+
+        fn disconnect(&mut self, ship: ShipId) {
+            self.clients.remove(&ship);
+            self.queue.extend(self.relay.disconnect(ship));
+        }
+
+        fn control(&mut self, ship: ShipId, throttle: Throttle, level: f32) {
+            let (lockstep, _) = self.clients.get_mut(&ship).unwrap();
+            lockstep.control(throttle, level);
+        }
+
+        fn exchange(&mut self) {
+            for (&ship, (lockstep, _)) in self.clients.iter_mut() {
+                while let Some(message) = lockstep.poll_outgoing() {
+                    self.queue.extend(self.relay.receive(ship, message));
+                }
+            }
+            for envelope in self.queue.drain(..) {
+                let text = envelope.message.encode();
+                for (&ship, (lockstep, _)) in self.clients.iter_mut() {
+                    if envelope.is_for(ship) {
+                        lockstep.receive(Message::decode(&text).unwrap());
+                    }
+                }
+            }
+        }
+
+        fn run(&mut self, ticks: u64) {
+            for _ in 0..ticks {
+                self.exchange();
+                for (lockstep, game) in self.clients.values_mut() {
+                    lockstep.advance(game);
+                }
+            }
+        }
+
+        /// Brings every client to the last step.
+        fn settle(&mut self) {
+            self.exchange();
+            for (lockstep, game) in self.clients.values_mut() {
+                while !lockstep.stalled() {
+                    lockstep.advance(game);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn clients_agree_on_every_ship() {
+        let mut network = Network {
+            relay: Relay::new(5, true),
+            clients: BTreeMap::new(),
+            queue: Vec::new(),
+        };
+        let first = network.connect();
+        assert_eq!(first, ShipId::PLAYER);
+        network.run(50);
+
+        let second = network.connect();
+        network.control(first, Throttle::Bottom, 1.0);
+        network.run(Game::countdown() + 20);
+        network.control(first, Throttle::Bottom, 1.0);
+        network.control(second, Throttle::Bottom, 1.0);
+        network.control(second, Throttle::Left, 1.0);
+        network.run(100);
+
+        let late = network.connect();
+        network.disconnect(second);
+        network.run(100);
+        network.settle();
+
+        let (a, _) = &network.clients[&first];
+        assert!(a.tick() > Game::countdown() + 200);
+        let ticks: Vec<u64> = network
+            .clients
+            .values()
+            .map(|(lockstep, _)| lockstep.tick())
+            .collect();
+        assert!(ticks.iter().all(|&tick| tick == ticks[0]));
+
+        let mut games: Vec<&mut Game> =
+            network.clients.values_mut().map(|(_, game)| game).collect();
+        assert_eq!(games[0].state_hash(), games[1].state_hash());
+        assert!(games[0].pilot(second).is_none());
+        assert!(games[0].pilot(late).is_some());
+        assert_eq!(games[0].state(), crate::game::State::Flying);
+        assert_eq!(network.relay.desync(), None);
+    }
+
+    #[test]
+    fn relay_reports_diverging_hashes() {
+        let mut relay = Relay::new(5, false);
+        let (a, _) = relay.connect();
+        let (b, _) = relay.connect();
+        let hash = |tick, hash| Message::Hash { tick, hash };
+        assert!(relay.receive(a, hash(100, 1)).is_empty());
+        assert!(relay.receive(b, hash(100, 1)).is_empty());
+        assert!(relay.receive(a, hash(200, 2)).is_empty());
+
+        let out = relay.receive(b, hash(200, 3));
+        assert_eq!(
+            out,
+            vec![Envelope::broadcast(Message::Desync { tick: 200 })]
+        );
+        assert!(relay.receive(b, hash(300, 4)).is_empty());
+        assert!(relay.receive(a, hash(300, 5)).is_empty());
+        assert_eq!(relay.desync(), Some(200));
+
+        let (mut lockstep, _) = Lockstep::join(relay.connect().1.remove(0).message).unwrap();
+        out.into_iter()
+            .for_each(|envelope| lockstep.receive(envelope.message));
+        assert_eq!(lockstep.desync(), Some(200));
+    }
+
+    #[test]
+    fn relay_forgets_hashes_every_client_reported() {
+        let mut relay = Relay::new(5, false);
+        let (a, _) = relay.connect();
+        let (b, _) = relay.connect();
+        let hash = |tick| Message::Hash { tick, hash: 1 };
+        for tick in (100..=1000).step_by(100) {
+            relay.receive(a, hash(tick));
+        }
+        assert_eq!(relay.hashes.len(), 10);
+        for tick in (100..=800).step_by(100) {
+            relay.receive(b, hash(tick));
+        }
+        assert_eq!(
+            relay.hashes.keys().copied().collect::<Vec<_>>(),
+            [900, 1000]
+        );
+
+        relay.disconnect(b);
+        assert!(relay.hashes.is_empty());
+        assert_eq!(relay.desync(), None);
+    }
+
+    #[test]
+    fn outdated_sessions_cannot_be_joined() {
+        let mut relay = Relay::new(5, false);
+        let welcome = relay.connect().1.remove(0).message;
+        assert!(Lockstep::join(welcome.clone()).is_some());
+
+        let outdated = match welcome {
+            Message::Welcome { ship, mut history } => {
+                history.version = 0;
+                Message::Welcome { ship, history }
+            }
+            _ => unreachable!(),
+        };
+        assert!(Lockstep::join(outdated).is_none());
+    }
+}
@@ -1,6 +1,43 @@
 use std::ops::DerefMut;
 
-use crate::geom::Mat3;
+use crate::{geom::Mat3, render::scene::Color};
+
+/// Rectangle of the target to draw into, in pixels from the top left corner.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Viewport {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+impl Viewport {
+    pub fn full(size: (u32, u32)) -> Viewport {
+        Viewport {
+            x: 0,
+            y: 0,
+            width: size.0,
+            height: size.1,
+        }
+    }
+
+    /// Splits the target into `count` side by side columns.
+    pub fn columns(size: (u32, u32), count: u32) -> Vec<Viewport> {
+        let count = count.max(1);
+        let width = size.0 / count;
+        (0..count)
+            .map(|column| Viewport {
+                x: column * width,
+                y: 0,
+                width,
+                height: size.1,
+            })
+            .collect()
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
 
 pub trait RenderTarget {
     type RenderScene<T: RenderScene>: DerefMut<Target = T>;
@@ -12,17 +49,42 @@ pub trait RenderTarget {
     where
         R: RenderScene;
 
-    fn render_one<'a, R>(
+    fn render_one<'a, R>(&'a mut self, scene: &'a mut Self::RenderScene<R>, context: R::Context<'a>)
+    where
+        R: RenderScene,
+    {
+        let viewport = Viewport::full(self.get_size());
+        self.render_viewports(vec![(viewport, scene, context)]);
+    }
+
+    /// Draws each scene into its own viewport of a single frame.
+    fn render_viewports<'a, R>(
         &'a mut self,
-        scene: &'a mut Self::RenderScene<R>,
-        context: R::Context<'a>,
+        views: Vec<(Viewport, &'a mut Self::RenderScene<R>, R::Context<'a>)>,
     ) where
         R: RenderScene;
 }
 
 pub trait RenderScene {
     type Context<'a>;
-    type Triangles<'a>: Iterator<Item = Mat3> + 'a;
+    type Triangles<'a>: Iterator<Item = (Mat3, Color)> + 'a;
 
     fn triangles<'a>(&'a self, context: Self::Context<'a>) -> Self::Triangles<'a>;
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn columns_split_the_width() {
+        let columns = Viewport::columns((801, 600), 2);
+        assert_eq!(columns.len(), 2);
+        assert_eq!(columns[0].size(), (400, 600));
+        assert_eq!(columns[1].x, 400);
+        assert_eq!(
+            Viewport::columns((800, 600), 1)[0],
+            Viewport::full((800, 600))
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use crate::geom::{Mat3, Point};
+use crate::geom::Mat3;
 
 use super::render_target::RenderScene;
 
@@ -12,25 +12,23 @@ impl Scene {
         }
     }
 
-    pub fn set_camera(&mut self, position: Point, zoom: f32, window: (u32, u32)) {
-        self.camera = Mat3::translate(-position.0, -position.1);
-        self.camera = Mat3::scale(zoom, zoom) * self.camera;
-        let aspect = (window.1 as f32) / (window.0 as f32);
-        self.camera = Mat3::scale(aspect, 1.0) * self.camera;
+    pub fn set_camera(&mut self, camera: Mat3) {
+        self.camera = camera;
     }
 }
 impl RenderScene for Scene {
     type Context<'a> = &'a mut [&'a mut dyn Drawable];
-    type Triangles<'a> = impl Iterator<Item = Mat3> + 'a;
+    type Triangles<'a> = impl Iterator<Item = (Mat3, Color)> + 'a;
 
     fn triangles<'a>(&'a self, context: Self::Context<'a>) -> Self::Triangles<'a> {
         let r = context
             .into_iter()
             .map(move |drawable| {
                 let position = drawable.position();
+                let color = drawable.color();
                 drawable
                     .triangles()
-                    .map(move |triangle| self.camera * position * triangle)
+                    .map(move |triangle| (self.camera * position * triangle, color))
             })
             .flatten();
 
@@ -38,9 +36,33 @@ impl RenderScene for Scene {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Color(pub f32, pub f32, pub f32, pub f32);
+impl Color {
+    pub fn white() -> Color {
+        Color(1.0, 1.0, 1.0, 1.0)
+    }
+
+    pub fn red() -> Color {
+        Color(1.0, 0.2, 0.2, 1.0)
+    }
+
+    pub fn with_alpha(self, alpha: f32) -> Color {
+        Color(self.0, self.1, self.2, alpha)
+    }
+
+    pub fn as_f32(&self) -> [f32; 4] {
+        [self.0, self.1, self.2, self.3]
+    }
+}
+
 pub trait Drawable {
     fn position(&self) -> Mat3;
     fn triangles<'a>(&'a mut self) -> &'a mut (dyn Iterator<Item = Mat3> + 'a);
+
+    fn color(&self) -> Color {
+        Color::white()
+    }
 }
 impl<T: Iterator<Item = Mat3>> Drawable for T {
     fn position(&self) -> Mat3 {
@@ -51,3 +73,18 @@ impl<T: Iterator<Item = Mat3>> Drawable for T {
         self
     }
 }
+
+pub struct Tinted<T: Drawable>(pub T, pub Color);
+impl<T: Drawable> Drawable for Tinted<T> {
+    fn position(&self) -> Mat3 {
+        self.0.position()
+    }
+
+    fn triangles<'a>(&'a mut self) -> &'a mut (dyn Iterator<Item = Mat3> + 'a) {
+        self.0.triangles()
+    }
+
+    fn color(&self) -> Color {
+        self.1
+    }
+}
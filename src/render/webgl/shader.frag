@@ -1,8 +1,9 @@
 precision highp float;
 precision highp int;
 
+uniform vec4 color;
+
 void main() {
-    gl_FragColor = vec4(1.0, 1.0, 1.0, 1.0);
+    gl_FragColor = color;
     return;
 }
-
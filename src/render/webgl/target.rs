@@ -1,7 +1,7 @@
 use wasm_bindgen::JsCast;
 use web_sys::{HtmlCanvasElement, WebGlRenderingContext};
 
-use crate::render::render_target::{RenderScene, RenderTarget};
+use crate::render::render_target::{RenderScene, RenderTarget, Viewport};
 
 use super::triangles::TriangleScene;
 
@@ -44,19 +44,30 @@ impl RenderTarget for WebglRenderTarget {
         TriangleScene::new(scene, &self.context)
     }
 
-    fn render_one<'a, R>(
+    fn render_viewports<'a, R>(
         &'a mut self,
-        scene: &'a mut TriangleScene<R>,
-        scene_context: R::Context<'a>,
+        views: Vec<(Viewport, &'a mut TriangleScene<R>, R::Context<'a>)>,
     ) where
         R: RenderScene,
     {
-        self.context.viewport(
-            0,
-            0,
-            self.canvas.width() as i32,
-            self.canvas.height() as i32,
-        );
-        scene.render_one(scene_context, &self.context);
+        let (width, height) = self.get_size();
+        self.context.viewport(0, 0, width as i32, height as i32);
+        self.context.clear_color(0.0, 0.0, 0.0, 1.0);
+        self.context.clear(WebGlRenderingContext::COLOR_BUFFER_BIT);
+
+        self.context.enable(WebGlRenderingContext::SCISSOR_TEST);
+        for (viewport, scene, scene_context) in views {
+            // WebGL counts rows from the bottom of the canvas.
+            let y = height as i32 - (viewport.y + viewport.height) as i32;
+            let (x, width, height) = (
+                viewport.x as i32,
+                viewport.width as i32,
+                viewport.height as i32,
+            );
+            self.context.viewport(x, y, width, height);
+            self.context.scissor(x, y, width, height);
+            scene.render_one(scene_context, &self.context);
+        }
+        self.context.disable(WebGlRenderingContext::SCISSOR_TEST);
     }
 }
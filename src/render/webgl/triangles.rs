@@ -4,11 +4,15 @@ use web_sys::{
     WebGlBuffer, WebGlProgram, WebGlRenderingContext, WebGlShader, WebGlUniformLocation,
 };
 
-use crate::{geom::Mat3, render::render_target::RenderScene};
+use crate::{
+    geom::Mat3,
+    render::{render_target::RenderScene, scene::Color},
+};
 
 pub struct TriangleScene<T: RenderScene> {
     program: WebGlProgram,
     uniform: WebGlUniformLocation,
+    color: WebGlUniformLocation,
     vertex_buffer: WebGlBuffer,
     vertex_location: i32,
     scene: T,
@@ -29,6 +33,7 @@ impl<T: RenderScene> TriangleScene<T> {
         .unwrap();
         let program = Self::link_program(context, &vert_shader, &frag_shader).unwrap();
         let uniform = context.get_uniform_location(&program, "matrix").unwrap();
+        let color = context.get_uniform_location(&program, "color").unwrap();
 
         let vertex_location = context.get_attrib_location(&program, "a_position");
         let vertex_buffer = context
@@ -52,16 +57,18 @@ impl<T: RenderScene> TriangleScene<T> {
         TriangleScene {
             program,
             uniform,
+            color,
             vertex_buffer,
             vertex_location,
             scene,
         }
     }
 
-    fn triangle_uniform(&self, transform: Mat3, context: &WebGlRenderingContext) {
+    fn triangle_uniform(&self, transform: Mat3, color: Color, context: &WebGlRenderingContext) {
         let transform = Mat3::scale_y(-1.0) * transform;
         let data = transform.as_f32_packed();
         context.uniform_matrix3fv_with_f32_array(Some(&self.uniform), false, &data);
+        context.uniform4fv_with_f32_array(Some(&self.color), &color.as_f32());
     }
 
     pub fn render_one<'a>(
@@ -70,8 +77,11 @@ impl<T: RenderScene> TriangleScene<T> {
         context: &WebGlRenderingContext,
     ) {
         context.use_program(Some(&self.program));
-        context.clear_color(0.0, 0.0, 0.0, 1.0);
-        context.clear(WebGlRenderingContext::COLOR_BUFFER_BIT);
+        context.enable(WebGlRenderingContext::BLEND);
+        context.blend_func(
+            WebGlRenderingContext::SRC_ALPHA,
+            WebGlRenderingContext::ONE_MINUS_SRC_ALPHA,
+        );
 
         context.bind_buffer(
             WebGlRenderingContext::ARRAY_BUFFER,
@@ -87,8 +97,8 @@ impl<T: RenderScene> TriangleScene<T> {
         );
         context.enable_vertex_attrib_array(self.vertex_location as u32);
 
-        for triangle in self.scene.triangles(scene_context) {
-            self.triangle_uniform(triangle, context);
+        for (triangle, color) in self.scene.triangles(scene_context) {
+            self.triangle_uniform(triangle, color, context);
             context.draw_arrays(WebGlRenderingContext::TRIANGLES, 0, 3);
         }
     }
@@ -5,6 +5,7 @@ struct Pos {
 [[block]]
 struct Transform {
     matrix: mat3x3<f32>;
+    color: vec4<f32>;
 };
 [[group(0), binding(0)]]
 var transform: Transform;
@@ -41,5 +42,5 @@ fn vs_line([[builtin(vertex_index)]] idx: u32) -> Pos {
 
 [[stage(fragment)]]
 fn fs_main() -> [[location(0)]] vec4<f32> {
-    return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+    return transform.color;
 }
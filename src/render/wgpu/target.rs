@@ -1,7 +1,7 @@
 use raw_window_handle::HasRawWindowHandle;
 use wgpu::TextureViewDescriptor;
 
-use crate::render::render_target::{RenderScene, RenderTarget};
+use crate::render::render_target::{RenderScene, RenderTarget, Viewport};
 
 use super::triangles::TriangleScene;
 
@@ -84,22 +84,28 @@ impl RenderTarget for WgpuRenderTarget {
         TriangleScene::new(scene, &self.device, &self.queue, self.swapchain_format)
     }
 
-    fn render_one<'a, R: RenderScene>(
+    fn render_viewports<'a, R: RenderScene>(
         &'a mut self,
-        scene: &'a mut TriangleScene<R>,
-        context: R::Context<'a>,
+        views: Vec<(Viewport, &'a mut TriangleScene<R>, R::Context<'a>)>,
     ) {
         let frame = self
             .surface
             .get_current_frame()
             .expect("Failed to acquire next swap chain texture")
             .output;
+        let view = frame.texture.create_view(&TextureViewDescriptor::default());
 
-        scene.render_one(
-            context,
-            &self.device,
-            &self.queue,
-            &frame.texture.create_view(&TextureViewDescriptor::default()),
-        );
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        let mut clear = true;
+        for (viewport, scene, context) in views {
+            if viewport.width == 0 || viewport.height == 0 {
+                continue;
+            }
+            scene.render_one(context, &self.device, &mut encoder, &view, viewport, clear);
+            clear = false;
+        }
+        self.queue.submit(Some(encoder.finish()));
     }
 }
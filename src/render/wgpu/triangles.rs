@@ -5,7 +5,13 @@ use std::{
 
 use wgpu::util::DeviceExt;
 
-use crate::{geom::Mat3, render::render_target::RenderScene};
+use crate::{
+    geom::Mat3,
+    render::{
+        render_target::{RenderScene, Viewport},
+        scene::Color,
+    },
+};
 
 pub struct TriangleScene<T: RenderScene> {
     render_pipeline: wgpu::RenderPipeline,
@@ -28,11 +34,11 @@ impl<T: RenderScene> TriangleScene<T> {
             label: None,
             entries: &[wgpu::BindGroupLayoutEntry {
                 binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
                 ty: wgpu::BindingType::Buffer {
                     ty: wgpu::BufferBindingType::Uniform,
                     has_dynamic_offset: false,
-                    min_binding_size: wgpu::BufferSize::new(4 * 12),
+                    min_binding_size: wgpu::BufferSize::new(4 * 16),
                 },
                 count: None,
             }],
@@ -55,7 +61,11 @@ impl<T: RenderScene> TriangleScene<T> {
             fragment: Some(wgpu::FragmentState {
                 module: &shader,
                 entry_point: "fs_main",
-                targets: &[target_format.into()],
+                targets: &[wgpu::ColorTargetState {
+                    format: target_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }],
             }),
             primitive: wgpu::PrimitiveState {
                 topology: wgpu::PrimitiveTopology::TriangleList,
@@ -73,51 +83,67 @@ impl<T: RenderScene> TriangleScene<T> {
         }
     }
 
+    /// Records a pass drawing the scene into `viewport`, clearing the whole
+    /// frame first when `clear` is set.
     pub fn render_one<'a>(
         &'a self,
         scene_context: T::Context<'a>,
         device: &wgpu::Device,
-        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
         view: &wgpu::TextureView,
+        viewport: Viewport,
+        clear: bool,
     ) {
-        let mut encoder =
-            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
-
-        {
-            let triangles: Vec<_> = self
-                .scene
-                .triangles(scene_context)
-                .map(|transform| self.triangle_bind_group(device, transform))
-                .collect();
-
-            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
-                color_attachments: &[wgpu::RenderPassColorAttachment {
-                    view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                        store: true,
-                    },
-                }],
-                depth_stencil_attachment: None,
-            });
-
-            rpass.set_pipeline(&self.render_pipeline);
-
-            triangles.iter().for_each(|bind| {
-                rpass.set_bind_group(0, &bind, &[]);
-                rpass.draw(0..3, 0..1);
-            });
-        }
+        let triangles: Vec<_> = self
+            .scene
+            .triangles(scene_context)
+            .map(|(transform, color)| self.triangle_bind_group(device, transform, color))
+            .collect();
+
+        let load = if clear {
+            wgpu::LoadOp::Clear(wgpu::Color::BLACK)
+        } else {
+            wgpu::LoadOp::Load
+        };
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations { load, store: true },
+            }],
+            depth_stencil_attachment: None,
+        });
 
-        queue.submit(Some(encoder.finish()));
+        rpass.set_pipeline(&self.render_pipeline);
+        rpass.set_viewport(
+            viewport.x as f32,
+            viewport.y as f32,
+            viewport.width as f32,
+            viewport.height as f32,
+            0.0,
+            1.0,
+        );
+        rpass.set_scissor_rect(viewport.x, viewport.y, viewport.width, viewport.height);
+
+        triangles.iter().for_each(|bind| {
+            rpass.set_bind_group(0, &bind, &[]);
+            rpass.draw(0..3, 0..1);
+        });
     }
 
-    fn triangle_bind_group(&self, device: &wgpu::Device, transform: Mat3) -> wgpu::BindGroup {
+    fn triangle_bind_group(
+        &self,
+        device: &wgpu::Device,
+        transform: Mat3,
+        color: Color,
+    ) -> wgpu::BindGroup {
+        let mut uniform = [0.0f32; 16];
+        uniform[..12].copy_from_slice(&transform.as_f32());
+        uniform[12..].copy_from_slice(&color.as_f32());
         let uniform_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Uniform Buffer"),
-            contents: to_u8(&transform.as_f32()),
+            contents: to_u8(&uniform),
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
@@ -0,0 +1,218 @@
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{
+    game::{Game, Mission, ShipId},
+    score::ScoreRules,
+    ship::{definition::ShipDefinition, Throttle, ThrusterModel},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Input {
+    pub tick: u64,
+    #[serde(default)]
+    pub ship: ShipId,
+    pub throttle: Throttle,
+    #[serde(alias = "activate", deserialize_with = "level")]
+    pub level: f32,
+}
+
+/// Reads a thruster level, or the on/off switch older replays stored.
+fn level<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Level {
+        Switch(bool),
+        Level(f32),
+    }
+
+    Ok(match Level::deserialize(deserializer)? {
+        Level::Switch(on) => on as u8 as f32,
+        Level::Level(level) => level,
+    })
+}
+
+/// A ship joining or leaving the game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Membership {
+    pub tick: u64,
+    pub ship: ShipId,
+    pub present: bool,
+}
+
+/// Everything needed to play a session back: the terrain seed and each
+/// control change stamped with the tick it happened on.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    /// Physics the session was flown with. Replays recorded before versions
+    /// were kept read as 0.
+    #[serde(default)]
+    pub version: u32,
+    pub seed: u64,
+    pub inputs: Vec<Input>,
+    #[serde(default)]
+    pub roster: Vec<Membership>,
+    /// Whether ships collided with each other, for the whole session.
+    #[serde(default)]
+    pub collisions: bool,
+    /// How thrusters behaved. Replays older than the model flew instant ones.
+    #[serde(default = "ThrusterModel::instant")]
+    pub thrusters: ThrusterModel,
+    /// Craft every ship flew. Replays older than definitions flew the classic
+    /// lander.
+    #[serde(default)]
+    pub craft: ShipDefinition,
+    #[serde(default)]
+    pub mission: Mission,
+    pub ticks: u64,
+}
+impl Replay {
+    pub fn new(seed: u64) -> Replay {
+        Replay {
+            version: Self::current_version(),
+            seed,
+            ..Default::default()
+        }
+    }
+
+    /// Bumped whenever ships fly differently on the same inputs, so older
+    /// replays are refused instead of playing back a different flight.
+    pub fn current_version() -> u32 {
+        2
+    }
+
+    pub fn is_current(&self) -> bool {
+        self.version == Self::current_version()
+    }
+
+    /// Longest session a replay may claim, an hour of flight, so a forged
+    /// tick count is refused instead of played back forever.
+    pub fn max_ticks() -> u64 {
+        60 * 60 * 100
+    }
+
+    pub fn record(&mut self, tick: u64, ship: ShipId, throttle: Throttle, level: f32) {
+        self.inputs.push(Input {
+            tick,
+            ship,
+            throttle,
+            level,
+        });
+    }
+
+    pub fn membership(&mut self, tick: u64, ship: ShipId, present: bool) {
+        self.roster.push(Membership {
+            tick,
+            ship,
+            present,
+        });
+    }
+
+    pub fn simulate(&self, rules: &ScoreRules) -> Game {
+        self.simulate_with(rules, |_| {})
+    }
+
+    /// Plays the session back, handing the game to `each` before the first
+    /// tick and after every one.
+    pub fn simulate_with<F: FnMut(&mut Game)>(&self, rules: &ScoreRules, mut each: F) -> Game {
+        let mut game = Game::with_seed(self.seed);
+        game.rules = rules.clone();
+        game.set_collisions(self.collisions);
+        game.set_thrusters(self.thrusters);
+        game.set_craft(self.craft.clone());
+        game.set_mission(self.mission);
+        each(&mut game);
+
+        let mut inputs = self.inputs.iter().peekable();
+        let mut roster = self.roster.iter().peekable();
+        loop {
+            while let Some(change) = roster.next_if(|change| change.tick <= game.tick()) {
+                if change.present {
+                    game.join(change.ship);
+                } else {
+                    game.remove_ship(change.ship);
+                }
+            }
+            while let Some(input) = inputs.next_if(|input| input.tick <= game.tick()) {
+                game.control_ship(input.ship, input.throttle, input.level);
+            }
+            if game.tick() >= self.ticks {
+                break;
+            }
+            game.advance();
+            each(&mut game);
+        }
+        game
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn simulation_reproduces_the_session() {
+        let mut game = Game::with_seed(7);
+        let script = [
+            (10, Throttle::Bottom, 1.0),
+            (320, Throttle::Bottom, 1.0),
+            (400, Throttle::Left, 1.0),
+            (420, Throttle::Left, 0.0),
+            (600, Throttle::Bottom, 0.0),
+        ];
+        for &(tick, throttle, level) in script.iter() {
+            while game.tick() < tick {
+                game.advance();
+            }
+            game.control(throttle, level);
+        }
+        (0..1500).for_each(|_| {
+            game.advance();
+        });
+
+        let replay = game.replay().unwrap();
+        assert_eq!(replay.inputs.len(), script.len());
+
+        let mut simulated = replay.simulate(&game.rules);
+        assert_eq!(simulated.tick(), game.tick());
+        assert_eq!(simulated.state(), game.state());
+        assert_eq!(simulated.ship().origin().0, game.ship().origin().0);
+        assert_eq!(simulated.ship().origin().1, game.ship().origin().1);
+        assert_eq!(simulated.replay(), Some(replay));
+        assert_eq!(
+            std::iter::from_fn(|| simulated.poll_event()).last(),
+            Some(game.state())
+        );
+    }
+
+    #[test]
+    fn older_replays_switch_thrusters() {
+        let json = r#"{"tick":3,"throttle":"Left","activate":true}"#;
+        let input: Input = serde_json::from_str(json).unwrap();
+        assert_eq!(input.level, 1.0);
+        assert_eq!(input.ship, ShipId::PLAYER);
+
+        let input = Input {
+            level: 0.25,
+            ..input
+        };
+        let json = serde_json::to_string(&input).unwrap();
+        assert_eq!(serde_json::from_str::<Input>(&json).unwrap(), input);
+    }
+
+    #[test]
+    fn replays_with_broken_crafts_are_refused() {
+        let mut replay = Replay::new(1);
+        replay.craft.thrusters[0].mounts = vec![5];
+        let json = serde_json::to_string(&replay).unwrap();
+        assert!(serde_json::from_str::<Replay>(&json).is_err());
+    }
+
+    #[test]
+    fn older_replays_are_not_current() {
+        assert!(Replay::new(1).is_current());
+        let json = r#"{"seed":1,"inputs":[],"ticks":10}"#;
+        let replay: Replay = serde_json::from_str(json).unwrap();
+        assert_eq!(replay.version, 0);
+        assert!(!replay.is_current());
+    }
+}
@@ -0,0 +1,262 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    geom::{Point, Vector},
+    terrain::Pad,
+};
+
+/// Awards `weight` points for a perfect value, falling linearly to nothing
+/// once the value reaches `limit`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    pub weight: f32,
+    pub limit: f32,
+}
+impl Rule {
+    pub fn score(&self, value: f32) -> f32 {
+        if self.limit <= 0.0 {
+            return 0.0;
+        }
+        self.weight * (1.0 - value.abs() / self.limit).max(0.0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScoreRules {
+    pub base: f32,
+    pub vertical_speed: Rule,
+    pub horizontal_speed: Rule,
+    pub attitude: Rule,
+    pub pad_distance: Rule,
+    /// Points per second of fuel left in the tank.
+    pub fuel: f32,
+    pub off_pad_multiplier: f32,
+    /// Points for setting a cargo down on its target pad, paid whatever pad
+    /// the ship itself landed on.
+    #[serde(default = "ScoreRules::delivery_bonus")]
+    pub delivery: f32,
+}
+impl Default for ScoreRules {
+    fn default() -> ScoreRules {
+        ScoreRules {
+            base: 100.0,
+            vertical_speed: Rule {
+                weight: 200.0,
+                limit: 20.0,
+            },
+            horizontal_speed: Rule {
+                weight: 100.0,
+                limit: 10.0,
+            },
+            attitude: Rule {
+                weight: 100.0,
+                limit: 0.3,
+            },
+            pad_distance: Rule {
+                weight: 100.0,
+                limit: 30.0,
+            },
+            fuel: 5.0,
+            off_pad_multiplier: 0.0,
+            delivery: Self::delivery_bonus(),
+        }
+    }
+}
+impl ScoreRules {
+    /// Also what rules tuned before deliveries existed pay for one.
+    fn delivery_bonus() -> f32 {
+        300.0
+    }
+
+    pub fn score(&self, touchdown: &Touchdown) -> Breakdown {
+        let (pad_distance, multiplier) = match touchdown.pad {
+            Some(pad) => (
+                self.pad_distance.score(touchdown.position.0 - pad.center.0),
+                pad.multiplier,
+            ),
+            None => (0.0, self.off_pad_multiplier),
+        };
+
+        let mut breakdown = Breakdown {
+            base: self.base,
+            vertical_speed: self.vertical_speed.score(touchdown.velocity.1),
+            horizontal_speed: self.horizontal_speed.score(touchdown.velocity.0),
+            attitude: self.attitude.score(touchdown.attitude),
+            pad_distance,
+            fuel: self.fuel * touchdown.fuel.max(0.0),
+            multiplier,
+            delivery: if touchdown.delivered {
+                self.delivery
+            } else {
+                0.0
+            },
+            total: 0,
+        };
+        let subtotal = breakdown.base
+            + breakdown.vertical_speed
+            + breakdown.horizontal_speed
+            + breakdown.attitude
+            + breakdown.pad_distance
+            + breakdown.fuel;
+        breakdown.total = (subtotal * multiplier + breakdown.delivery)
+            .max(0.0)
+            .round() as u32;
+        breakdown
+    }
+}
+
+/// State of the ship when its legs first touched the ground, plus where it
+/// settled.
+#[derive(Clone, Copy, Debug)]
+pub struct Touchdown {
+    pub velocity: Vector,
+    pub attitude: f32,
+    pub position: Point,
+    pub pad: Option<Pad>,
+    pub fuel: f32,
+    /// Whether the ship's cargo sat on its target pad.
+    pub delivered: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Breakdown {
+    pub base: f32,
+    pub vertical_speed: f32,
+    pub horizontal_speed: f32,
+    pub attitude: f32,
+    pub pad_distance: f32,
+    pub fuel: f32,
+    pub multiplier: f32,
+    #[serde(default)]
+    pub delivery: f32,
+    pub total: u32,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Run {
+    landings: Vec<Breakdown>,
+    total: u32,
+}
+impl Run {
+    pub fn new() -> Run {
+        Default::default()
+    }
+
+    pub fn record(&mut self, breakdown: Breakdown) {
+        self.total += breakdown.total;
+        self.landings.push(breakdown);
+    }
+
+    pub fn total(&self) -> u32 {
+        self.total
+    }
+
+    pub fn landings(&self) -> &[Breakdown] {
+        &self.landings
+    }
+
+    pub fn last(&self) -> Option<&Breakdown> {
+        self.landings.last()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn pad(multiplier: f32) -> Pad {
+        Pad {
+            center: Point(100.0, 0.0),
+            width: 40.0,
+            multiplier,
+        }
+    }
+
+    fn touchdown() -> Touchdown {
+        Touchdown {
+            velocity: Point(0.0, 0.0),
+            attitude: 0.0,
+            position: Point(100.0, 0.0),
+            pad: Some(pad(1.0)),
+            fuel: 0.0,
+            delivered: false,
+        }
+    }
+
+    #[test]
+    fn perfect_landing_scores_every_weight() {
+        let rules = ScoreRules::default();
+        let breakdown = rules.score(&touchdown());
+        assert_eq!(breakdown.vertical_speed, rules.vertical_speed.weight);
+        assert_eq!(breakdown.attitude, rules.attitude.weight);
+        assert_eq!(breakdown.total, 600);
+    }
+
+    #[test]
+    fn rougher_landings_score_less() {
+        let rules = ScoreRules::default();
+        let mut hard = touchdown();
+        hard.velocity = Point(-5.0, -10.0);
+        hard.attitude = -0.15;
+        hard.position = Point(115.0, 0.0);
+
+        let breakdown = rules.score(&hard);
+        assert_eq!(breakdown.vertical_speed, 100.0);
+        assert_eq!(breakdown.horizontal_speed, 50.0);
+        assert!((breakdown.attitude - 50.0).abs() < 1e-3);
+        assert_eq!(breakdown.pad_distance, 50.0);
+        assert_eq!(breakdown.total, 350);
+
+        hard.velocity = Point(0.0, -25.0);
+        assert_eq!(rules.score(&hard).vertical_speed, 0.0);
+    }
+
+    #[test]
+    fn pads_and_fuel_change_the_total() {
+        let rules = ScoreRules::default();
+        let mut landing = touchdown();
+        landing.pad = Some(pad(3.0));
+        landing.fuel = 20.0;
+        assert_eq!(rules.score(&landing).total, (600 + 100) * 3);
+
+        landing.pad = None;
+        assert_eq!(rules.score(&landing).total, 0);
+
+        let rules = ScoreRules {
+            off_pad_multiplier: 0.5,
+            ..ScoreRules::default()
+        };
+        assert_eq!(rules.score(&landing).total, (500 + 100) / 2);
+    }
+
+    #[test]
+    fn deliveries_pay_off_the_pad_too() {
+        let rules = ScoreRules::default();
+        let mut landing = touchdown();
+        landing.pad = Some(pad(2.0));
+        landing.delivered = true;
+        assert_eq!(rules.score(&landing).total, 600 * 2 + 300);
+
+        landing.pad = None;
+        assert_eq!(rules.score(&landing).total, 300);
+    }
+
+    #[test]
+    fn rules_tuned_before_deliveries_still_load() {
+        let mut json = serde_json::to_value(ScoreRules::default()).unwrap();
+        json.as_object_mut().unwrap().remove("delivery");
+        let rules: ScoreRules = serde_json::from_value(json).unwrap();
+        assert_eq!(rules, ScoreRules::default());
+    }
+
+    #[test]
+    fn run_accumulates_landings() {
+        let rules = ScoreRules::default();
+        let mut run = Run::new();
+        run.record(rules.score(&touchdown()));
+        run.record(rules.score(&touchdown()));
+        assert_eq!(run.total(), 1200);
+        assert_eq!(run.landings().len(), 2);
+        assert_eq!(run.last().unwrap().total, 600);
+    }
+}
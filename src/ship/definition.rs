@@ -0,0 +1,228 @@
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    constraint::Solver,
+    geom::{Mat3, Point, Vector},
+    ship::Throttle,
+};
+
+/// A point of the hull that carries weight and feels the ground.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MassPoint {
+    pub name: String,
+    /// Where it sits with the ship upright, from the middle of its legs.
+    pub position: Point,
+    #[serde(default = "MassPoint::unit")]
+    pub mass: f32,
+}
+impl MassPoint {
+    fn unit() -> f32 {
+        1.0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ThrusterMount {
+    pub name: String,
+    /// Control that fires it.
+    pub throttle: Throttle,
+    /// Mass points it pushes, each with its full force.
+    pub mounts: Vec<usize>,
+    /// Where its flame comes out, in the ship's frame.
+    pub nozzle: Point,
+    /// Which way it pushes, in the ship's frame.
+    #[serde(default = "ThrusterMount::up")]
+    pub direction: Vector,
+    /// Force at full throttle, in multiples of gravity.
+    pub power: f32,
+}
+impl ThrusterMount {
+    fn up() -> Vector {
+        Point(0.0, 1.0)
+    }
+}
+
+/// Layout of a craft: its hull, how it is pushed around and how it looks.
+/// Definitions are validated as they are read, so a bad one from a file, a
+/// stored replay or the network is refused rather than flown.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(remote = "Self")]
+pub struct ShipDefinition {
+    pub name: String,
+    pub points: Vec<MassPoint>,
+    /// Pairs of points held at their rest distance, also outlining the hull
+    /// that other ships run into.
+    pub constraints: Vec<(usize, usize)>,
+    /// Solver passes per step holding the hull together.
+    #[serde(default = "ShipDefinition::iterations")]
+    pub iterations: u32,
+    /// Points it lands on, left then right.
+    pub legs: (usize, usize),
+    /// Point that tells which way is up.
+    pub nose: usize,
+    pub thrusters: Vec<ThrusterMount>,
+    /// Seconds of a single thruster firing at full level on a full tank.
+    pub fuel: f32,
+    /// Triangles drawing the hull, in the ship's frame.
+    pub mesh: Vec<[Point; 3]>,
+}
+impl Serialize for ShipDefinition {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ShipDefinition::serialize(self, serializer)
+    }
+}
+impl<'de> Deserialize<'de> for ShipDefinition {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<ShipDefinition, D::Error> {
+        let craft = ShipDefinition::deserialize(deserializer)?;
+        craft.validate().map_err(de::Error::custom)?;
+        Ok(craft)
+    }
+}
+impl Default for ShipDefinition {
+    fn default() -> ShipDefinition {
+        ShipDefinition::classic()
+    }
+}
+impl ShipDefinition {
+    /// The original three point lander.
+    pub fn classic() -> ShipDefinition {
+        Self::from_json(include_str!("classic.json")).unwrap()
+    }
+
+    /// Slow and wide, with a big tank.
+    pub fn heavy() -> ShipDefinition {
+        Self::from_json(include_str!("heavy.json")).unwrap()
+    }
+
+    /// Light and twitchy, with a small tank.
+    pub fn scout() -> ShipDefinition {
+        Self::from_json(include_str!("scout.json")).unwrap()
+    }
+
+    fn iterations() -> u32 {
+        10
+    }
+
+    pub fn builtin() -> Vec<ShipDefinition> {
+        vec![Self::classic(), Self::heavy(), Self::scout()]
+    }
+
+    pub fn named(name: &str) -> Option<ShipDefinition> {
+        Self::builtin().into_iter().find(|craft| craft.name == name)
+    }
+
+    pub fn from_json(json: &str) -> Result<ShipDefinition, String> {
+        serde_json::from_str(json).map_err(|e| e.to_string())
+    }
+
+    /// Whether this is one of the crafts that come with the game.
+    pub fn is_builtin(&self) -> bool {
+        Self::builtin().contains(self)
+    }
+
+    /// Checks every index points at a mass point and the frame is well
+    /// defined.
+    pub fn validate(&self) -> Result<(), String> {
+        let points = self.points.len();
+        let check = |index: usize, what: &str| {
+            if index < points {
+                Ok(())
+            } else {
+                Err(format!("{} refers to missing point {}", what, index))
+            }
+        };
+
+        check(self.legs.0, "left leg")?;
+        check(self.legs.1, "right leg")?;
+        check(self.nose, "nose")?;
+        if self.legs.0 == self.legs.1 || self.nose == self.legs.0 || self.nose == self.legs.1 {
+            return Err(String::from("legs and nose must be different points"));
+        }
+        if let Some(point) = self.points.iter().find(|point| point.mass <= 0.0) {
+            return Err(format!("{} must have some mass", point.name));
+        }
+        for &(a, b) in &self.constraints {
+            check(a, "constraint")?;
+            check(b, "constraint")?;
+        }
+        for thruster in &self.thrusters {
+            if thruster.mounts.is_empty() {
+                return Err(format!("{} is not mounted anywhere", thruster.name));
+            }
+            for &mount in &thruster.mounts {
+                check(mount, &thruster.name)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Solver holding the hull in its rest shape.
+    pub fn solver(&self) -> Solver {
+        let masses: Vec<f32> = self.points.iter().map(|point| point.mass).collect();
+        let rest: Vec<Point> = self.points.iter().map(|point| point.position).collect();
+        let mut solver = Solver::new(&masses, self.iterations);
+        for &(a, b) in &self.constraints {
+            solver.keep_distance(&rest, a, b);
+        }
+        solver
+    }
+
+    /// Middle of the hull at rest.
+    pub fn rest_center(&self) -> Point {
+        let legs = (self.points[self.legs.0].position + self.points[self.legs.1].position) * 0.5;
+        (legs + self.points[self.nose].position) * 0.5
+    }
+
+    /// Transforms taking the unit triangle the renderers draw onto each
+    /// triangle of the mesh.
+    pub fn triangles(&self) -> Vec<Mat3> {
+        self.mesh
+            .iter()
+            .map(|&[a, b, c]| Mat3::triangle(a, b, c))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_crafts_are_valid() {
+        let crafts = ShipDefinition::builtin();
+        assert_eq!(crafts.len(), 3);
+        assert!(crafts.iter().all(|craft| craft.validate().is_ok()));
+        assert_eq!(
+            ShipDefinition::named("scout"),
+            Some(ShipDefinition::scout())
+        );
+        assert_eq!(ShipDefinition::named("shuttle"), None);
+    }
+
+    #[test]
+    fn broken_definitions_are_refused() {
+        let mut craft = ShipDefinition::classic();
+        craft.thrusters[0].mounts.push(7);
+        assert!(craft.validate().is_err());
+
+        let mut craft = ShipDefinition::classic();
+        craft.nose = craft.legs.0;
+        assert!(craft.validate().is_err());
+
+        assert!(ShipDefinition::from_json("{}").is_err());
+    }
+
+    #[test]
+    fn broken_definitions_are_refused_when_read() {
+        let mut craft = ShipDefinition::classic();
+        craft.legs = (0, 9);
+        let json = serde_json::to_string(&craft).unwrap();
+        let error = ShipDefinition::from_json(&json).unwrap_err();
+        assert!(error.contains("missing point 9"));
+
+        let json = serde_json::to_string(&ShipDefinition::heavy()).unwrap();
+        let craft: ShipDefinition = serde_json::from_str(&json).unwrap();
+        assert!(craft.is_builtin());
+        assert!(!ShipDefinition { fuel: 1e6, ..craft }.is_builtin());
+    }
+}
@@ -0,0 +1,854 @@
+pub mod definition;
+
+use std::{collections::BTreeMap, sync::Arc};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use rand::prelude::Distribution;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    constraint::Solver,
+    geom::{Line, Mat3, Point, Vector},
+    hash::{StateHash, StateHasher},
+    inertia::Inertia,
+    math,
+    render::scene::{Color, Drawable, Tinted},
+    ship::definition::{ShipDefinition, ThrusterMount},
+    terrain::{generator, Terrain},
+};
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Throttle {
+    Left,
+    Bottom,
+    Right,
+}
+
+/// Parts of the ship that wear down on impacts, indexing the mass points and
+/// thrusters of its craft.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Part {
+    Point(usize),
+    Thruster(usize),
+}
+
+/// Readings of a ship's state for displays and logs.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Telemetry {
+    pub fuel: f32,
+    pub speed: f32,
+    pub attitude: f32,
+    /// Keyed by thruster name.
+    pub thrusters: BTreeMap<String, Thruster>,
+    /// Keyed by part name, from 1 for intact to 0 for broken.
+    pub health: BTreeMap<String, f32>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Impact {
+    pub position: Point,
+    pub velocity: Vector,
+}
+impl Impact {
+    pub fn speed(&self) -> f32 {
+        self.velocity.len()
+    }
+}
+
+/// How thrusters answer their throttle. Replays keep the model they were
+/// flown with, so it stays the same for a whole session.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ThrusterModel {
+    /// Seconds from idle to full thrust.
+    pub spool_up: f32,
+    /// Seconds from full thrust back to idle.
+    pub spool_down: f32,
+    /// Heat a thruster takes before cutting out, firing at full adding 1 per
+    /// second. Zero never overheats.
+    pub heat_capacity: f32,
+    /// Heat shed per second.
+    pub cooling: f32,
+    /// Chance per second of firing that a thruster sputters out.
+    pub misfires: f32,
+    /// Chance per second of firing while fully hot that a thruster breaks
+    /// for the rest of the flight.
+    pub failures: f32,
+}
+impl Default for ThrusterModel {
+    fn default() -> ThrusterModel {
+        ThrusterModel {
+            spool_up: 0.3,
+            spool_down: 0.15,
+            heat_capacity: 5.0,
+            cooling: 0.5,
+            misfires: 0.0,
+            failures: 0.0,
+        }
+    }
+}
+impl ThrusterModel {
+    /// Full thrust the moment the throttle opens, as older replays flew.
+    pub fn instant() -> ThrusterModel {
+        ThrusterModel {
+            spool_up: 0.0,
+            spool_down: 0.0,
+            heat_capacity: 0.0,
+            cooling: 0.0,
+            misfires: 0.0,
+            failures: 0.0,
+        }
+    }
+
+    /// Thrusters that sputter and break down, for hard games.
+    pub fn hard() -> ThrusterModel {
+        ThrusterModel {
+            misfires: 0.1,
+            failures: 0.05,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct Thruster {
+    level: f32,
+    output: f32,
+    heat: f32,
+    overheated: bool,
+    failed: bool,
+}
+impl Thruster {
+    /// Throttle asked of the thruster, from 0 to 1.
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    /// Thrust it actually gives, from 0 to 1.
+    pub fn output(&self) -> f32 {
+        self.output
+    }
+
+    pub fn heat(&self) -> f32 {
+        self.heat
+    }
+
+    /// Cut out until it cools down to half its capacity.
+    pub fn overheated(&self) -> bool {
+        self.overheated
+    }
+
+    pub fn failed(&self) -> bool {
+        self.failed
+    }
+
+    /// Runs one step of the model, calling `roll` for a number between 0 and
+    /// 1 whenever it needs chance.
+    fn update<R: FnMut() -> f32>(&mut self, model: &ThrusterModel, mut roll: R) {
+        let dt = Inertia::step();
+        let target = if self.overheated || self.failed {
+            0.0
+        } else {
+            self.level
+        };
+        let time = if target > self.output {
+            model.spool_up
+        } else {
+            model.spool_down
+        };
+        self.output = if time <= 0.0 {
+            target
+        } else if target > self.output {
+            (self.output + dt / time).min(target)
+        } else {
+            (self.output - dt / time).max(target)
+        };
+
+        if self.output > 0.0 && model.misfires > 0.0 && roll() < model.misfires * dt {
+            self.output = 0.0;
+        }
+
+        if model.heat_capacity <= 0.0 {
+            return;
+        }
+        self.heat = (self.heat + (self.output - model.cooling) * dt).max(0.0);
+        if self.heat >= model.heat_capacity {
+            self.overheated = true;
+        } else if self.heat <= model.heat_capacity * 0.5 {
+            self.overheated = false;
+        }
+        let hot = self.heat / model.heat_capacity;
+        if self.output > 0.0 && model.failures > 0.0 && roll() < model.failures * dt * hot {
+            self.failed = true;
+        }
+    }
+
+    fn release(&mut self) {
+        self.level = 0.0;
+        self.output = 0.0;
+    }
+}
+impl StateHash for Thruster {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        hasher.write_f32(self.level);
+        hasher.write_f32(self.output);
+        hasher.write_f32(self.heat);
+        hasher.write_bool(self.overheated);
+        hasher.write_bool(self.failed);
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Ship {
+    craft: Arc<ShipDefinition>,
+    solver: Arc<Solver>,
+    /// Indexed like the craft's mass points.
+    points: Vec<Inertia>,
+    /// Indexed like the craft's thrusters.
+    thrusters: Vec<Thruster>,
+    contacts: Vec<bool>,
+    fuel: f32,
+    /// Mass points first, then thrusters, as `Part` indexes them.
+    health: Vec<f32>,
+    /// Seeds the chance of thrusters misfiring and failing, along with how
+    /// many numbers it has given.
+    seed: u64,
+    rolls: i64,
+    /// Seed of the session the ship flies in, mixed into the rolls. The
+    /// replay already pins it down, so it stays out of the state hash.
+    session: u64,
+}
+impl Ship {
+    /// A classic lander with the middle of its legs at `position`.
+    pub fn spawn(position: Point) -> Ship {
+        Ship::with_craft(Arc::new(ShipDefinition::default()), position)
+    }
+
+    pub fn with_craft(craft: Arc<ShipDefinition>, position: Point) -> Ship {
+        Ship {
+            points: craft
+                .points
+                .iter()
+                .map(|point| Inertia::new(point.position + position))
+                .collect(),
+            thrusters: vec![Thruster::default(); craft.thrusters.len()],
+            contacts: vec![false; craft.points.len()],
+            fuel: craft.fuel,
+            health: vec![1.0; craft.points.len() + craft.thrusters.len()],
+            seed: (position.0.to_bits() as u64) << 32 | position.1.to_bits() as u64,
+            rolls: 0,
+            session: 0,
+            solver: Arc::new(craft.solver()),
+            craft,
+        }
+    }
+
+    /// Mixes `session` into the chance of misfires, so sessions starting from
+    /// the same pad do not all break down the same way.
+    pub fn reseed(&mut self, session: u64) {
+        self.session = session;
+    }
+
+    pub fn interpolated(&self, alpha: f32) -> Ship {
+        Ship {
+            points: self
+                .points
+                .iter()
+                .map(|point| point.interpolate(alpha))
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    pub fn craft(&self) -> &ShipDefinition {
+        &self.craft
+    }
+
+    pub fn origin(&self) -> Point {
+        let (left, right) = self.craft.legs;
+        (self.points[left].position + self.points[right].position) * 0.5
+    }
+
+    pub fn center(&self) -> Point {
+        (self.origin() + self.points[self.craft.nose].position) * 0.5
+    }
+
+    pub fn hull(&self) -> Vec<Line> {
+        self.craft
+            .constraints
+            .iter()
+            .map(|&(a, b)| Line(self.points[a].position, self.points[b].position))
+            .collect()
+    }
+
+    pub fn touches(&self, other: &Ship) -> bool {
+        let theirs = other.hull();
+        self.hull()
+            .iter()
+            .any(|edge| theirs.iter().any(|other| edge.intersects(*other)))
+    }
+
+    /// Moves the ship without changing its previous position, so the offset
+    /// also becomes velocity.
+    pub fn nudge(&mut self, offset: Vector) {
+        self.points.iter_mut().for_each(|point| {
+            point.position = point.position + offset;
+        });
+    }
+
+    pub fn direction(&self) -> Vector {
+        let origin = self.origin();
+        let (left, right) = self.craft.legs;
+        let dir1 = (self.points[self.craft.nose].position - origin).unit();
+        let dir2 = (self.points[left].position - self.points[right].position)
+            .unit()
+            .rot90();
+        (dir1 + dir2) * 0.5
+    }
+
+    pub fn velocity(&self) -> Vector {
+        let (left, right) = self.craft.legs;
+        (self.points[left].inertia() + self.points[right].inertia()) * (0.5 / Inertia::step())
+    }
+
+    pub fn attitude(&self) -> f32 {
+        let direction = self.direction();
+        math::atan2(direction.0, direction.1)
+    }
+
+    pub fn upright(&self) -> bool {
+        self.direction().unit().1 > 0.95
+    }
+
+    pub fn grounded(&self) -> bool {
+        self.contacts.iter().any(|&contact| contact)
+    }
+
+    pub fn resting(&self) -> bool {
+        let (left, right) = self.craft.legs;
+        self.contacts[left]
+            && self.contacts[right]
+            && !self.capsized()
+            && self.upright()
+            && self.velocity().len() < 1.0
+    }
+
+    /// Touching the ground with anything but its legs.
+    pub fn capsized(&self) -> bool {
+        self.contacts
+            .iter()
+            .enumerate()
+            .any(|(idx, &contact)| contact && !self.is_leg(idx))
+    }
+
+    fn is_leg(&self, point: usize) -> bool {
+        point == self.craft.legs.0 || point == self.craft.legs.1
+    }
+
+    pub fn integrate(&mut self, land: &mut dyn Terrain, model: &ThrusterModel) -> Option<Impact> {
+        self.points.iter_mut().for_each(|point| {
+            land.apply_gravity(point);
+        });
+
+        self.spool(model);
+        self.burn();
+        self.push();
+
+        self.points.iter_mut().for_each(Inertia::integrate);
+        self.solver.solve(&mut self.points);
+        self.ground_collision(land)
+    }
+
+    pub(crate) fn gravity() -> Vector {
+        Point(0.0, -0.32)
+    }
+
+    pub fn crash_speed() -> f32 {
+        20.0
+    }
+
+    pub fn max_fuel(&self) -> f32 {
+        self.craft.fuel
+    }
+
+    pub fn fuel(&self) -> f32 {
+        self.fuel
+    }
+
+    pub fn refuel(&mut self) {
+        self.fuel = self.max_fuel();
+    }
+
+    /// Impacts slower than this leave the hull untouched.
+    pub fn safe_speed() -> f32 {
+        8.0
+    }
+
+    /// How much lower a broken leg lets its side of the hull sit.
+    pub fn leg_sag() -> f32 {
+        1.5
+    }
+
+    /// Every part of the craft, mass points first.
+    pub fn parts(&self) -> impl Iterator<Item = Part> {
+        let (points, thrusters) = (self.craft.points.len(), self.craft.thrusters.len());
+        (0..points)
+            .map(Part::Point)
+            .chain((0..thrusters).map(Part::Thruster))
+    }
+
+    pub fn part_name(&self, part: Part) -> &str {
+        match part {
+            Part::Point(idx) => &self.craft.points[idx].name,
+            Part::Thruster(idx) => &self.craft.thrusters[idx].name,
+        }
+    }
+
+    fn slot(&self, part: Part) -> usize {
+        match part {
+            Part::Point(idx) => idx,
+            Part::Thruster(idx) => self.craft.points.len() + idx,
+        }
+    }
+
+    pub fn health(&self, part: Part) -> f32 {
+        self.health[self.slot(part)]
+    }
+
+    pub fn repair(&mut self) {
+        self.health.iter_mut().for_each(|health| *health = 1.0);
+    }
+
+    fn wear(&mut self, part: Part, amount: f32) {
+        let slot = self.slot(part);
+        let health = &mut self.health[slot];
+        *health = (*health - amount).max(0.0);
+    }
+
+    /// Wears down each hull point that hit the ground at `speeds`, passing
+    /// half of the blow on to the thrusters mounted on it.
+    fn take_hits(&mut self, speeds: &[f32]) {
+        let craft = self.craft.clone();
+        for (idx, &speed) in speeds.iter().enumerate() {
+            let range = Self::crash_speed() - Self::safe_speed();
+            let damage = (speed - Self::safe_speed()) / range * 0.5;
+            if damage <= 0.0 {
+                continue;
+            }
+            self.wear(Part::Point(idx), damage);
+            for (thruster, mount) in craft.thrusters.iter().enumerate() {
+                if mount.mounts.contains(&idx) {
+                    let share = damage * 0.5 / mount.mounts.len() as f32;
+                    self.wear(Part::Thruster(thruster), share);
+                }
+            }
+        }
+    }
+
+    pub fn telemetry(&self) -> Telemetry {
+        Telemetry {
+            fuel: self.fuel,
+            speed: self.velocity().len(),
+            attitude: self.attitude(),
+            thrusters: self
+                .craft
+                .thrusters
+                .iter()
+                .zip(&self.thrusters)
+                .map(|(mount, &thruster)| (mount.name.clone(), thruster))
+                .collect(),
+            health: self
+                .parts()
+                .map(|part| (self.part_name(part).to_string(), self.health(part)))
+                .collect(),
+        }
+    }
+
+    fn spool(&mut self, model: &ThrusterModel) {
+        let seed = generator::hash(self.session, self.seed as i64);
+        let rolls = &mut self.rolls;
+        let mut roll = || {
+            *rolls += 1;
+            generator::random(seed, *rolls)
+        };
+        self.thrusters
+            .iter_mut()
+            .for_each(|thruster| thruster.update(model, &mut roll));
+    }
+
+    fn burn(&mut self) {
+        let burn = self
+            .thrusters
+            .iter()
+            .map(|thruster| thruster.output)
+            .sum::<f32>()
+            * Inertia::step();
+        if burn > self.fuel {
+            self.fuel = 0.0;
+            self.release();
+        } else {
+            self.fuel -= burn;
+        }
+    }
+
+    /// Applies every thruster's force to the points it is mounted on, which
+    /// heavier points feel less.
+    fn push(&mut self) {
+        let direction = self.direction();
+        let side = direction.rot90();
+        let forces: Vec<Vector> = self
+            .craft
+            .thrusters
+            .iter()
+            .enumerate()
+            .map(|(idx, mount)| {
+                let heading = side * mount.direction.0 + direction * mount.direction.1;
+                let force = Self::gravity().len() * mount.power;
+                let health = self.health(Part::Thruster(idx));
+                heading * (force * (self.thrusters[idx].output * health))
+            })
+            .collect();
+        for (mount, force) in self.craft.thrusters.iter().zip(forces) {
+            for &point in &mount.mounts {
+                let mass = self.craft.points[point].mass;
+                self.points[point].force(force * (1.0 / mass));
+            }
+        }
+    }
+
+    fn ground_collision(&mut self, land: &mut dyn Terrain) -> Option<Impact> {
+        let mut impact: Option<Impact> = None;
+        let mut contacts = vec![false; self.points.len()];
+        let mut speeds = vec![0.0; self.points.len()];
+        // A broken leg touches down from higher up the hull, so its side sinks.
+        let direction = self.direction();
+        let lifts: Vec<Vector> = (0..self.points.len())
+            .map(|idx| {
+                let health = if self.is_leg(idx) {
+                    self.health(Part::Point(idx))
+                } else {
+                    1.0
+                };
+                direction * (Self::leg_sag() * (1.0 - health))
+            })
+            .collect();
+        self.points.iter_mut().enumerate().for_each(|(idx, point)| {
+            let before = point.position;
+            let mut foot = point.position + lifts[idx];
+            land.handle_collision(&mut foot);
+            point.position = foot - lifts[idx];
+            if (point.position - before).len() == 0.0 {
+                return;
+            }
+            contacts[idx] = true;
+
+            let velocity = (before - point.prev) * (1.0 / Inertia::step());
+            speeds[idx] = velocity.len();
+            match impact {
+                Some(impact) if impact.speed() >= velocity.len() => {}
+                _ => {
+                    impact = Some(Impact {
+                        position: point.position,
+                        velocity,
+                    })
+                }
+            }
+        });
+        self.contacts = contacts;
+        self.take_hits(&speeds);
+        impact
+    }
+
+    /// Fires every thruster bound to `throttle` at `level`, clamped between 0
+    /// and 1.
+    pub fn throttle(&mut self, throttle: Throttle, level: f32) {
+        let level = if self.fuel > 0.0 {
+            level.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        for (thruster, mount) in self.thrusters.iter_mut().zip(&self.craft.thrusters) {
+            if mount.throttle == throttle {
+                thruster.level = level;
+            }
+        }
+    }
+
+    /// Level of the first thruster bound to `throttle`.
+    pub fn level(&self, throttle: Throttle) -> f32 {
+        self.craft
+            .thrusters
+            .iter()
+            .zip(&self.thrusters)
+            .find(|(mount, _)| mount.throttle == throttle)
+            .map_or(0.0, |(_, thruster)| thruster.level)
+    }
+
+    pub fn thruster(&self, idx: usize) -> &Thruster {
+        &self.thrusters[idx]
+    }
+
+    /// Shuts every thruster off at once.
+    pub fn release(&mut self) {
+        self.thrusters.iter_mut().for_each(Thruster::release);
+    }
+
+    pub fn drawable(&self) -> impl Drawable + '_ {
+        ShipDrawable(self, self.craft.triangles().into_iter())
+    }
+
+    /// Red marks over the worn parts, growing as they break.
+    pub fn damage_marks(&self) -> impl Drawable + '_ {
+        let marks: Vec<Mat3> = self
+            .parts()
+            .filter(|&part| self.health(part) < 1.0)
+            .map(|part| {
+                let size = 1.0 - self.health(part);
+                let at = self.mark(part);
+                Mat3::translate(at.0, at.1) * Mat3::scale(size, size)
+            })
+            .collect();
+        Tinted(ShipDrawable(self, marks.into_iter()), Color::red())
+    }
+
+    /// Where a part's damage shows, a little inside the hull.
+    fn mark(&self, part: Part) -> Point {
+        match part {
+            Part::Point(idx) => {
+                let at = self.craft.points[idx].position;
+                at + (self.craft.rest_center() - at) * 0.15
+            }
+            Part::Thruster(idx) => {
+                let mount = &self.craft.thrusters[idx];
+                mount.nozzle + mount.direction
+            }
+        }
+    }
+
+    pub fn active_throttles(&self) -> impl Drawable + '_ {
+        let flames: Vec<Mat3> = self
+            .craft
+            .thrusters
+            .iter()
+            .zip(&self.thrusters)
+            .map(|(mount, thruster)| Self::flame(mount, thruster))
+            .collect();
+
+        ShipDrawable(self, flames.into_iter())
+    }
+
+    fn flame(mount: &ThrusterMount, thruster: &Thruster) -> Mat3 {
+        let level = thruster.output;
+        let size = if level > 0.0 {
+            let mut rng = rand::thread_rng();
+            let between = rand::distributions::Uniform::from(100..300);
+            level * (between.sample(&mut rng) as f32) / 100.0
+        } else {
+            0.0
+        };
+
+        Mat3::translate(mount.nozzle.0, mount.nozzle.1)
+            * Mat3::rotate_y_to(mount.direction)
+            * Mat3::scale(0.5, -size)
+    }
+}
+
+impl StateHash for Ship {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        self.points
+            .iter()
+            .for_each(|point| point.hash_state(hasher));
+        self.thrusters
+            .iter()
+            .for_each(|thruster| thruster.hash_state(hasher));
+        self.contacts
+            .iter()
+            .for_each(|&contact| hasher.write_bool(contact));
+        hasher.write_f32(self.fuel);
+        self.health
+            .iter()
+            .for_each(|&health| hasher.write_f32(health));
+        hasher.write_u64(self.seed);
+        hasher.write_u64(self.rolls as u64);
+    }
+}
+
+pub struct ShipDrawable<'a, T: Iterator<Item = Mat3>>(&'a Ship, T);
+impl<'a, T: Iterator<Item = Mat3>> Drawable for ShipDrawable<'a, T> {
+    fn position(&self) -> crate::geom::Mat3 {
+        let origin = self.0.origin();
+
+        Mat3::translate(origin.0, origin.1) * Mat3::rotate_y_to(self.0.direction())
+    }
+
+    fn triangles<'b>(&'b mut self) -> &'b mut (dyn Iterator<Item = Mat3> + 'b) {
+        &mut self.1
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::terrain::polyline::Polyline;
+
+    fn land() -> Polyline {
+        Polyline::new(vec![Point(-1000.0, 0.0), Point(1000.0, 0.0)])
+    }
+
+    fn fire(ship: &mut Ship, model: &ThrusterModel, seconds: f32) {
+        let mut land = land();
+        for _ in 0..(seconds / Inertia::step()).round() as u32 {
+            ship.integrate(&mut land, model);
+        }
+    }
+
+    #[test]
+    fn thrust_and_burn_follow_the_level() {
+        let mut land = land();
+        let mut full = Ship::spawn(Point(0.0, 100.0));
+        let mut half = full.clone();
+        let mut idle = full.clone();
+        full.throttle(Throttle::Bottom, 1.0);
+        half.throttle(Throttle::Bottom, 0.5);
+        half.throttle(Throttle::Left, 2.0);
+        half.throttle(Throttle::Left, 0.0);
+        let model = ThrusterModel::instant();
+        for _ in 0..100 {
+            full.integrate(&mut land, &model);
+            half.integrate(&mut land, &model);
+            idle.integrate(&mut land, &model);
+        }
+
+        let burnt = |ship: &Ship| ship.max_fuel() - ship.fuel();
+        assert!((burnt(&full) / burnt(&half) - 2.0).abs() < 0.01);
+        assert_eq!(burnt(&idle), 0.0);
+
+        let lift = |ship: &Ship| ship.velocity().1 - idle.velocity().1;
+        assert!((lift(&full) / lift(&half) - 2.0).abs() < 0.01);
+        assert!(lift(&half) > 0.0);
+    }
+
+    #[test]
+    fn thrusters_spool_and_overheat() {
+        let model = ThrusterModel::default();
+        let mut ship = Ship::spawn(Point(0.0, 1000.0));
+        ship.throttle(Throttle::Bottom, 1.0);
+        let output = |ship: &Ship| ship.thruster(1).output();
+
+        fire(&mut ship, &model, model.spool_up * 0.5);
+        assert!((output(&ship) - 0.5).abs() < 0.05);
+        fire(&mut ship, &model, model.spool_up);
+        assert_eq!(output(&ship), 1.0);
+
+        fire(&mut ship, &model, 10.5);
+        assert!(ship.thruster(1).overheated());
+        assert_eq!(output(&ship), 0.0);
+        fire(&mut ship, &model, 5.5);
+        assert!(!ship.thruster(1).overheated());
+        assert_eq!(output(&ship), 1.0);
+
+        ship.throttle(Throttle::Bottom, 0.0);
+        fire(&mut ship, &model, model.spool_down * 0.5);
+        assert!((output(&ship) - 0.5).abs() < 0.05);
+    }
+
+    #[test]
+    fn hard_thrusters_fail_the_same_way_every_time() {
+        let model = ThrusterModel {
+            misfires: 2.0,
+            failures: 50.0,
+            ..ThrusterModel::hard()
+        };
+        let mut ship = Ship::spawn(Point(0.0, 1000.0));
+        ship.throttle(Throttle::Bottom, 1.0);
+        let mut again = ship.clone();
+        let mut elsewhere = ship.clone();
+        elsewhere.reseed(1);
+
+        let mut hashes = (StateHasher::default(), StateHasher::default());
+        let mut other = StateHasher::default();
+        for _ in 0..500 {
+            fire(&mut ship, &model, Inertia::step());
+            fire(&mut again, &model, Inertia::step());
+            fire(&mut elsewhere, &model, Inertia::step());
+            ship.hash_state(&mut hashes.0);
+            again.hash_state(&mut hashes.1);
+            elsewhere.hash_state(&mut other);
+        }
+        assert_eq!(hashes.0.finish(), hashes.1.finish());
+        assert_ne!(hashes.0.finish(), other.finish());
+        assert!(ship.thruster(1).failed());
+        assert_eq!(ship.thruster(1).output(), 0.0);
+    }
+
+    #[test]
+    fn hard_landings_wear_parts_down() {
+        let model = ThrusterModel::instant();
+        let mut ship = Ship::spawn(Point(0.0, 0.5));
+        ship.nudge(Point(0.0, -0.12));
+        fire(&mut ship, &model, 0.5);
+
+        let leg = ship.health(Part::Point(0));
+        assert!(leg < 1.0);
+        assert_eq!(ship.health(Part::Point(1)), leg);
+        assert!(ship.health(Part::Thruster(0)) > leg);
+        assert!(ship.health(Part::Thruster(1)) < 1.0);
+        assert_eq!(ship.health(Part::Point(2)), 1.0);
+        assert_eq!(ship.telemetry().health["left leg"], leg);
+
+        let mut gentle = Ship::spawn(Point(0.0, 0.5));
+        fire(&mut gentle, &model, 0.5);
+        assert!(gentle.parts().all(|part| gentle.health(part) == 1.0));
+    }
+
+    #[test]
+    fn broken_parts_change_how_the_ship_flies() {
+        let model = ThrusterModel::instant();
+        let mut limping = Ship::spawn(Point(0.0, 0.0));
+        limping.wear(Part::Point(0), 1.0);
+        fire(&mut limping, &model, 3.0);
+        assert!(limping.attitude() < -0.1);
+        assert!(limping.grounded());
+
+        let mut intact = Ship::spawn(Point(0.0, 100.0));
+        let mut damaged = intact.clone();
+        let mut idle = intact.clone();
+        damaged.wear(Part::Thruster(1), 0.5);
+        intact.throttle(Throttle::Bottom, 1.0);
+        damaged.throttle(Throttle::Bottom, 1.0);
+        fire(&mut intact, &model, 1.0);
+        fire(&mut damaged, &model, 1.0);
+        fire(&mut idle, &model, 1.0);
+        let lift = |ship: &Ship| ship.velocity().1 - idle.velocity().1;
+        assert!((lift(&intact) / lift(&damaged) - 2.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn crafts_fly_by_their_own_layout() {
+        let model = ThrusterModel::instant();
+        let lift = |craft: ShipDefinition| {
+            let mut idle = Ship::with_craft(Arc::new(craft), Point(0.0, 100.0));
+            let mut ship = idle.clone();
+            ship.throttle(Throttle::Bottom, 1.0);
+            fire(&mut ship, &model, 1.0);
+            fire(&mut idle, &model, 1.0);
+            assert!(ship.upright());
+            ship.velocity().1 - idle.velocity().1
+        };
+        let (classic, heavy, scout) = (
+            lift(ShipDefinition::classic()),
+            lift(ShipDefinition::heavy()),
+            lift(ShipDefinition::scout()),
+        );
+        assert!(heavy > 0.0);
+        assert!(heavy < classic && classic < scout);
+
+        let mut heavy = Ship::with_craft(Arc::new(ShipDefinition::heavy()), Point(0.0, 100.0));
+        heavy.throttle(Throttle::Bottom, 1.0);
+        assert_eq!(heavy.thruster(1).level(), 1.0);
+        assert_eq!(heavy.thruster(2).level(), 1.0);
+        assert_eq!(heavy.thruster(0).level(), 0.0);
+        assert_eq!(heavy.hull().len(), 7);
+        assert_eq!(heavy.telemetry().health.len(), 9);
+    }
+}
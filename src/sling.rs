@@ -0,0 +1,244 @@
+use std::sync::Arc;
+
+use crate::{
+    constraint::{Constraint, Solver},
+    geom::{Line, Mat3, Point},
+    hash::{StateHash, StateHasher},
+    inertia::Inertia,
+    ship::Ship,
+    terrain::Terrain,
+};
+
+/// A cargo hanging on a rope of chained points from a hook between a ship's
+/// legs. The rope pulls the whole ship back as much as the ship pulls the
+/// cargo along, so a swinging load sways it without flipping it over.
+#[derive(Clone, Debug)]
+pub struct Sling {
+    /// The hook under the ship first and the cargo last, with the rope's
+    /// knots in between.
+    points: Vec<Inertia>,
+    solver: Arc<Solver>,
+    /// Whether the cargo touched the ground on the last step.
+    grounded: bool,
+}
+impl Sling {
+    pub fn knots() -> usize {
+        6
+    }
+
+    pub fn length() -> f32 {
+        30.0
+    }
+
+    pub fn cargo_mass() -> f32 {
+        0.5
+    }
+
+    fn knot_mass() -> f32 {
+        0.05
+    }
+
+    /// Share of its speed a knot or the cargo loses every step it drags on
+    /// the ground.
+    fn friction() -> f32 {
+        0.2
+    }
+
+    /// Lays the rope out straight from `ship` toward the land a rope length
+    /// to its right, every knot at rest and the cargo at the far end.
+    pub fn new(land: &mut dyn Terrain, ship: &Ship) -> Sling {
+        let hook = ship.origin();
+        let segment = Self::length() / Self::knots() as f32;
+        let x = hook.0 + Self::length();
+        let direction = (Point(x, land.height(x)) - hook).unit();
+        let points: Vec<Inertia> = (0..=Self::knots())
+            .map(|idx| Inertia::new(hook + direction * (segment * idx as f32)))
+            .collect();
+
+        let mut masses = vec![ship.craft().points.iter().map(|point| point.mass).sum()];
+        masses.resize(Self::knots(), Self::knot_mass());
+        masses.push(Self::cargo_mass());
+
+        let mut solver = Solver::new(&masses, 20);
+        for idx in 0..Self::knots() {
+            solver.add(Constraint::Distance {
+                a: idx,
+                b: idx + 1,
+                length: segment,
+            });
+        }
+
+        Sling {
+            points,
+            solver: Arc::new(solver),
+            grounded: true,
+        }
+    }
+
+    pub fn interpolated(&self, alpha: f32) -> Sling {
+        Sling {
+            points: self
+                .points
+                .iter()
+                .map(|point| point.interpolate(alpha))
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    pub fn cargo(&self) -> Point {
+        self.points[Self::knots()].position
+    }
+
+    /// Cargo sitting still on the ground.
+    pub fn landed(&self) -> bool {
+        let speed = self.points[Self::knots()].inertia().len() / Inertia::step();
+        self.grounded && speed < 1.0
+    }
+
+    pub fn rope(&self) -> Vec<Line> {
+        self.points
+            .windows(2)
+            .map(|pair| Line(pair[0].position, pair[1].position))
+            .collect()
+    }
+
+    /// Runs one step after `ship` moved, dragging the ship along as the rope
+    /// goes taut.
+    pub fn update(&mut self, land: &mut dyn Terrain, ship: &mut Ship) {
+        self.points[1..].iter_mut().for_each(|point| {
+            land.apply_gravity(point);
+            point.integrate();
+        });
+
+        let hook = ship.origin();
+        self.points[0] = Inertia::new(hook);
+        self.solver.solve(&mut self.points);
+        ship.nudge(self.points[0].position - hook);
+
+        self.grounded = false;
+        for (idx, point) in self.points.iter_mut().enumerate().skip(1) {
+            let before = point.position;
+            land.handle_collision(&mut point.position);
+            if (point.position - before).len() == 0.0 {
+                continue;
+            }
+            point.prev = point.position - point.inertia() * (1.0 - Self::friction());
+            if idx == Self::knots() {
+                self.grounded = true;
+            }
+        }
+    }
+
+    /// The rope as thin strips and the cargo as a crate standing on its end.
+    pub fn drawable(&self) -> impl Iterator<Item = Mat3> {
+        let rope = self.rope().into_iter().map(|line| {
+            let pos = line.center();
+            Mat3::translate(pos.0, pos.1)
+                * Mat3::rotate_y_to(line.direction().rot90() * -1.0)
+                * Mat3::scale(line.len() * 0.5, 0.3)
+        });
+
+        let Point(x, y) = self.cargo();
+        let size = 1.5;
+        let crate_box = vec![
+            Mat3::triangle(
+                Point(x - size, y),
+                Point(x - size, y + size * 2.0),
+                Point(x + size, y),
+            ),
+            Mat3::triangle(
+                Point(x - size, y + size * 2.0),
+                Point(x + size, y + size * 2.0),
+                Point(x + size, y),
+            ),
+        ];
+        rope.chain(crate_box)
+    }
+}
+impl StateHash for Sling {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        self.points
+            .iter()
+            .for_each(|point| point.hash_state(hasher));
+        hasher.write_bool(self.grounded);
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{ship::Throttle, ship::ThrusterModel, terrain::polyline::Polyline};
+
+    fn land() -> Polyline {
+        Polyline::new(vec![Point(-1000.0, 0.0), Point(1000.0, 0.0)])
+    }
+
+    fn fly(ship: &mut Ship, sling: &mut Sling, seconds: f32) {
+        let mut land = land();
+        for _ in 0..(seconds / Inertia::step()).round() as u32 {
+            ship.integrate(&mut land, &ThrusterModel::instant());
+            sling.update(&mut land, ship);
+        }
+    }
+
+    #[test]
+    fn rope_lifts_the_cargo_off_the_ground() {
+        let mut land = land();
+        let mut ship = Ship::spawn(Point(0.0, 0.0));
+        let mut sling = Sling::new(&mut land, &ship);
+        assert!(sling.landed());
+        assert_eq!(sling.cargo().0, Sling::length());
+
+        ship.throttle(Throttle::Bottom, 1.0);
+        fly(&mut ship, &mut sling, 4.0);
+        assert!(!sling.grounded);
+        assert!(sling.cargo().1 > 5.0);
+        let hang = (ship.origin() - sling.cargo()).len();
+        assert!(hang > Sling::length() * 0.9 && hang < Sling::length() * 1.05);
+        assert!(sling
+            .rope()
+            .iter()
+            .all(|knot| (knot.len() - Sling::length() / Sling::knots() as f32).abs() < 0.5));
+    }
+
+    #[test]
+    fn rope_is_laid_out_at_rest_on_slopes() {
+        let mut slope = Polyline::new(vec![Point(-1000.0, -500.0), Point(1000.0, 500.0)]);
+        let ship = Ship::spawn(Point(0.0, 0.0));
+        let sling = Sling::new(&mut slope, &ship);
+        let segment = Sling::length() / Sling::knots() as f32;
+        assert!(sling
+            .rope()
+            .iter()
+            .all(|knot| (knot.len() - segment).abs() < 1e-4));
+        let cargo = sling.cargo();
+        assert!((cargo.1 - slope.height(cargo.0)).abs() < 0.5);
+    }
+
+    #[test]
+    fn cargo_weighs_the_ship_down() {
+        let mut land = land();
+        let mut free = Ship::spawn(Point(0.0, 0.0));
+        let mut loaded = free.clone();
+        let mut sling = Sling::new(&mut land, &loaded);
+        free.throttle(Throttle::Bottom, 1.0);
+        loaded.throttle(Throttle::Bottom, 1.0);
+        for _ in 0..400 {
+            free.integrate(&mut land, &ThrusterModel::instant());
+            loaded.integrate(&mut land, &ThrusterModel::instant());
+            sling.update(&mut land, &mut loaded);
+        }
+        assert!(loaded.origin().1 < free.origin().1 - 5.0);
+    }
+
+    #[test]
+    fn cargo_rests_on_the_land() {
+        let mut land = land();
+        let mut ship = Ship::spawn(Point(0.0, 0.0));
+        let mut sling = Sling::new(&mut land, &ship);
+        fly(&mut ship, &mut sling, 2.0);
+        assert!(sling.landed());
+        assert!(sling.cargo().1.abs() < 0.1);
+    }
+}
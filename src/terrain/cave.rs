@@ -0,0 +1,394 @@
+use std::{collections::HashMap, ops::Range};
+
+use crate::{
+    geom::{Line, Point},
+    terrain::{
+        generator::{Generator, MidpointDisplacement},
+        Terrain,
+    },
+};
+
+pub struct Polygon {
+    points: Vec<Point>,
+    min: Point,
+    max: Point,
+}
+impl Polygon {
+    pub fn new(mut points: Vec<Point>) -> Polygon {
+        assert!(points.len() >= 3);
+        if Self::signed_area(&points) > 0.0 {
+            points.reverse();
+        }
+
+        let min = points.iter().fold(points[0], |min, point| {
+            Point(min.0.min(point.0), min.1.min(point.1))
+        });
+        let max = points.iter().fold(points[0], |max, point| {
+            Point(max.0.max(point.0), max.1.max(point.1))
+        });
+
+        Polygon { points, min, max }
+    }
+
+    pub fn edge(&self, idx: usize) -> Line {
+        Line(self.points[idx], self.points[(idx + 1) % self.points.len()])
+    }
+
+    pub fn edges(&self) -> impl Iterator<Item = Line> + '_ {
+        (0..self.points.len()).map(move |idx| self.edge(idx))
+    }
+
+    pub fn contains(&self, pos: Point) -> bool {
+        if pos.0 < self.min.0 || pos.0 > self.max.0 || pos.1 < self.min.1 || pos.1 > self.max.1 {
+            return false;
+        }
+
+        self.edges()
+            .filter(|edge| (edge.0 .1 > pos.1) != (edge.1 .1 > pos.1))
+            .filter(|edge| edge.at_y(pos.1).0 > pos.0)
+            .count()
+            % 2
+            == 1
+    }
+
+    fn signed_area(points: &[Point]) -> f32 {
+        (0..points.len())
+            .map(|idx| {
+                let a = points[idx];
+                let b = points[(idx + 1) % points.len()];
+                a.0 * b.1 - b.0 * a.1
+            })
+            .sum::<f32>()
+            * 0.5
+    }
+}
+
+pub struct Cave {
+    polygons: Vec<Polygon>,
+    cell: f32,
+    grid: HashMap<(i32, i32), Vec<(usize, usize)>>,
+    /// Lowest and highest row of the grid.
+    rows: (i32, i32),
+}
+impl Cave {
+    pub fn new(polygons: Vec<Polygon>) -> Cave {
+        let mut cave = Cave {
+            polygons,
+            cell: 50.0,
+            grid: HashMap::new(),
+            rows: (0, 0),
+        };
+        cave.index();
+        cave
+    }
+
+    pub fn tunnel(floor: &dyn Generator, range: Range<i64>, clearance: f32) -> Cave {
+        let surface: Vec<Point> = range.map(|index| floor.point(index)).collect();
+        Cave::along(surface, clearance)
+    }
+
+    /// A long tunnel following seeded land, roomy enough to fly through, with
+    /// a flat floor to set off from at the origin.
+    pub fn level(seed: u64) -> Cave {
+        let mut floor = MidpointDisplacement::new(seed);
+        floor.amplitude = 60.0;
+        let base = floor.point(0).1;
+        let surface = (-32..2048)
+            .map(|index| match floor.point(index) {
+                Point(x, _) if (-2..=2).contains(&index) => Point(x, base),
+                point => point,
+            })
+            .collect();
+        Cave::along(surface, 150.0)
+    }
+
+    /// Ground under `surface` and a ceiling `clearance` above it.
+    fn along(surface: Vec<Point>, clearance: f32) -> Cave {
+        let bottom = surface.iter().fold(f32::MAX, |min, point| min.min(point.1)) - 100.0;
+        let top = surface.iter().fold(f32::MIN, |max, point| max.max(point.1)) + clearance + 100.0;
+        let (first, last) = (surface[0], *surface.last().unwrap());
+
+        let mut ground = surface.clone();
+        ground.push(Point(last.0, bottom));
+        ground.push(Point(first.0, bottom));
+
+        let mut ceiling: Vec<Point> = surface
+            .iter()
+            .map(|point| Point(point.0, point.1 + clearance))
+            .collect();
+        ceiling.push(Point(last.0, top));
+        ceiling.push(Point(first.0, top));
+
+        Cave::new(vec![Polygon::new(ground), Polygon::new(ceiling)])
+    }
+
+    pub fn add(&mut self, polygon: Polygon) {
+        self.polygons.push(polygon);
+        self.index();
+    }
+
+    fn index(&mut self) {
+        self.grid.clear();
+        self.rows = (i32::MAX, i32::MIN);
+        for (polygon_idx, polygon) in self.polygons.iter().enumerate() {
+            for (edge_idx, edge) in polygon.edges().enumerate() {
+                let (min, max) = (self.cell_of(edge.0), self.cell_of(edge.1));
+                for x in min.0.min(max.0)..=min.0.max(max.0) {
+                    for y in min.1.min(max.1)..=min.1.max(max.1) {
+                        self.rows = (self.rows.0.min(y), self.rows.1.max(y));
+                        self.grid
+                            .entry((x, y))
+                            .or_default()
+                            .push((polygon_idx, edge_idx));
+                    }
+                }
+            }
+        }
+    }
+
+    fn cell_of(&self, pos: Point) -> (i32, i32) {
+        (
+            (pos.0 / self.cell).floor() as i32,
+            (pos.1 / self.cell).floor() as i32,
+        )
+    }
+
+    /// Edges in the grid columns from `min` to `max`, each once and in
+    /// polygon order.
+    fn columns(&self, min: i32, max: i32) -> Vec<(usize, Line)> {
+        let rows = self.rows.0..=self.rows.1;
+        let mut edges: Vec<(usize, usize)> = (min..=max)
+            .flat_map(|x| rows.clone().map(move |y| (x, y)))
+            .filter_map(|cell| self.grid.get(&cell))
+            .flatten()
+            .copied()
+            .collect();
+        edges.sort_unstable();
+        edges.dedup();
+        edges
+            .into_iter()
+            .map(|(polygon, edge)| (polygon, self.polygons[polygon].edge(edge)))
+            .collect()
+    }
+
+    /// Edges crossing `x` where rock ends going up, from the bottom. Each
+    /// polygon is solid between alternate crossings, so overlapping rock
+    /// only ends once all of it does.
+    fn floors(&self, x: f32) -> Vec<Line> {
+        let column = self.cell_of(Point(x, 0.0)).0;
+        let mut crossings: Vec<(f32, usize, Line)> = self
+            .columns(column, column)
+            .into_iter()
+            .filter(|(_, edge)| (edge.0 .0 > x) != (edge.1 .0 > x))
+            .map(|(polygon, edge)| (edge.at_x(x).1, polygon, edge))
+            .collect();
+        crossings.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+        let mut inside = vec![false; self.polygons.len()];
+        let mut solid = 0;
+        let mut floors = Vec::new();
+        for (_, polygon, edge) in crossings {
+            inside[polygon] = !inside[polygon];
+            if inside[polygon] {
+                solid += 1;
+            } else {
+                solid -= 1;
+                if solid == 0 {
+                    floors.push(edge);
+                }
+            }
+        }
+        floors
+    }
+
+    fn nearest(&self, pos: Point, polygon: Option<usize>) -> Option<Line> {
+        let center = self.cell_of(pos);
+        let mut best: Option<(f32, Line)> = None;
+
+        for ring in 0..64 {
+            for x in (center.0 - ring)..=(center.0 + ring) {
+                for y in (center.1 - ring)..=(center.1 + ring) {
+                    if (x - center.0).abs() != ring && (y - center.1).abs() != ring {
+                        continue;
+                    }
+                    let edges = self.grid.get(&(x, y)).into_iter().flatten();
+                    for &(polygon_idx, edge_idx) in edges {
+                        if matches!(polygon, Some(polygon) if polygon != polygon_idx) {
+                            continue;
+                        }
+                        let edge = self.polygons[polygon_idx].edge(edge_idx);
+                        let distance = (edge.closest(pos) - pos).len();
+                        match best {
+                            Some((best, _)) if best <= distance => {}
+                            _ => best = Some((distance, edge)),
+                        }
+                    }
+                }
+            }
+            if let Some((distance, _)) = best {
+                if distance <= (ring as f32) * self.cell {
+                    break;
+                }
+            }
+        }
+
+        best.map(|(_, edge)| edge)
+    }
+}
+impl Terrain for Cave {
+    /// The floor under `pos`, or a line far below when there is none.
+    fn get(&mut self, pos: Point) -> Line {
+        self.floors(pos.0)
+            .into_iter()
+            .take_while(|floor| floor.at_x(pos.0).1 <= pos.1)
+            .last()
+            .unwrap_or_else(|| Line(pos + Point(-1.0, -1e6), pos + Point(1.0, -1e6)))
+    }
+
+    fn segments(&mut self, min: f32, max: f32) -> Vec<Line> {
+        let (first, last) = (
+            self.cell_of(Point(min, 0.0)).0,
+            self.cell_of(Point(max, 0.0)).0,
+        );
+        self.columns(first, last)
+            .into_iter()
+            .map(|(_, edge)| edge)
+            .filter(|edge| edge.0 .0.max(edge.1 .0) >= min && edge.0 .0.min(edge.1 .0) <= max)
+            .collect()
+    }
+
+    /// Top of the lowest floor at `x`, where the first rock going up from
+    /// below ends.
+    fn height(&mut self, x: f32) -> f32 {
+        self.floors(x)
+            .first()
+            .map_or(f32::MIN, |floor| floor.at_x(x).1)
+    }
+
+    fn handle_collision(&mut self, pos: &mut Point) {
+        let inside = self
+            .polygons
+            .iter()
+            .position(|polygon| polygon.contains(*pos));
+        if let Some(polygon) = inside {
+            if let Some(edge) = self.nearest(*pos, Some(polygon)) {
+                *pos = edge.closest(*pos);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn square(min: Point, size: f32) -> Polygon {
+        Polygon::new(vec![
+            min,
+            min + Point(size, 0.0),
+            min + Point(size, size),
+            min + Point(0.0, size),
+        ])
+    }
+
+    #[test]
+    fn polygons_contain_their_inside_only() {
+        let square = square(Point(0.0, 0.0), 10.0);
+        assert!(square.contains(Point(5.0, 5.0)));
+        assert!(!square.contains(Point(15.0, 5.0)));
+        assert!(!square.contains(Point(5.0, -1.0)));
+
+        let notch = Polygon::new(vec![
+            Point(0.0, 0.0),
+            Point(10.0, 0.0),
+            Point(10.0, 10.0),
+            Point(5.0, 2.0),
+            Point(0.0, 10.0),
+        ]);
+        assert!(notch.contains(Point(1.0, 3.0)));
+        assert!(!notch.contains(Point(5.0, 8.0)));
+    }
+
+    #[test]
+    fn nearest_edge_is_found_across_cells() {
+        let mut cave = Cave::new(vec![
+            square(Point(0.0, 0.0), 10.0),
+            square(Point(300.0, 0.0), 10.0),
+        ]);
+        let far = cave.nearest(Point(200.0, 5.0), None).unwrap();
+        assert_eq!(far.0 .0, 300.0);
+        assert_eq!(far.1 .0, 300.0);
+        assert_eq!(
+            cave.nearest(Point(200.0, 5.0), Some(0))
+                .map(|edge| edge.0 .0),
+            Some(10.0)
+        );
+    }
+
+    #[test]
+    fn ground_is_the_floor_below() {
+        let mut cave = Cave::new(vec![
+            square(Point(0.0, 0.0), 10.0),
+            square(Point(0.0, 20.0), 10.0),
+            square(Point(0.0, 25.0), 10.0),
+        ]);
+        let ground = |cave: &mut Cave, y: f32| cave.get(Point(5.0, y)).at_x(5.0).1;
+        assert_eq!(ground(&mut cave, 15.0), 10.0);
+        assert_eq!(ground(&mut cave, 28.0), 10.0);
+        assert_eq!(ground(&mut cave, 100.0), 35.0);
+        assert!(ground(&mut cave, -5.0) < -1e5);
+        assert_eq!(cave.height(5.0), 10.0);
+
+        // The overlapping squares are one rock, whose top is the floor.
+        let mut overlap = Cave::new(vec![
+            square(Point(0.0, 0.0), 10.0),
+            square(Point(0.0, 5.0), 10.0),
+        ]);
+        assert_eq!(overlap.height(5.0), 15.0);
+    }
+
+    #[test]
+    fn segments_come_from_the_cells_in_range() {
+        let mut cave = Cave::level(5);
+        for (min, max) in [(-100.0, 100.0), (220.0, 260.0), (5000.0, 5500.0)] {
+            let all: Vec<Line> = cave
+                .polygons
+                .iter()
+                .flat_map(|polygon| polygon.edges())
+                .filter(|edge| edge.0 .0.max(edge.1 .0) >= min && edge.0 .0.min(edge.1 .0) <= max)
+                .collect();
+            assert_eq!(cave.segments(min, max), all);
+        }
+    }
+
+    #[test]
+    fn points_inside_the_rock_are_pushed_out() {
+        let mut cave = Cave::new(vec![square(Point(0.0, 0.0), 10.0)]);
+        let mut pos = Point(5.0, 9.0);
+        cave.handle_collision(&mut pos);
+        assert_eq!(pos, Point(5.0, 10.0));
+
+        let mut pos = Point(5.0, 12.0);
+        cave.handle_collision(&mut pos);
+        assert_eq!(pos, Point(5.0, 12.0));
+    }
+
+    #[test]
+    fn levels_start_on_the_tunnel_floor() {
+        let mut cave = Cave::level(3);
+        let floor = cave.height(0.0);
+        let mut pos = Point(0.0, floor + 1.0);
+        cave.handle_collision(&mut pos);
+        assert_eq!(pos, Point(0.0, floor + 1.0));
+
+        let buried = Point(0.0, floor - 1.0);
+        let mut pos = buried;
+        cave.handle_collision(&mut pos);
+        let moved = (pos - buried).len();
+        assert!(moved > 0.0 && moved <= 1.0001);
+
+        let mut pos = Point(0.0, floor + 151.0);
+        cave.handle_collision(&mut pos);
+        assert!(pos.1 < floor + 151.0);
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    geom::Point,
+    terrain::{
+        generator::{hash, Generator, SPACING},
+        Pad,
+    },
+};
+
+pub const SAMPLES: i64 = 32;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Chunk {
+    pub index: i64,
+    pub points: Vec<Point>,
+    #[serde(default)]
+    pub pads: Vec<Pad>,
+}
+impl Chunk {
+    pub fn generate(generator: &dyn Generator, index: i64) -> Chunk {
+        let mut points: Vec<Point> = (index * SAMPLES..(index + 1) * SAMPLES)
+            .map(|sample| generator.point(sample))
+            .collect();
+        let pad = Self::flatten_pad(index, &mut points);
+        Chunk {
+            index,
+            points,
+            pads: vec![pad],
+        }
+    }
+
+    /// Levels a run of 2 to 4 segments into a landing pad. The layout is keyed
+    /// on the chunk's own terrain, so every seed places its pads differently.
+    /// Narrower pads pay a bigger multiplier.
+    fn flatten_pad(index: i64, points: &mut [Point]) -> Pad {
+        let roll = hash(points[0].1.to_bits() as u64, index);
+        let segments = 2 + (roll % 3) as usize;
+        let start = 2 + ((roll >> 8) % (SAMPLES as u64 - 8)) as usize;
+
+        let run = &mut points[start..=start + segments];
+        let height = run.iter().map(|point| point.1).sum::<f32>() / run.len() as f32;
+        run.iter_mut().for_each(|point| point.1 = height);
+
+        let (left, right) = (run[0].0, run[segments].0);
+        Pad {
+            center: Point((left + right) * 0.5, height),
+            width: right - left,
+            multiplier: (5 - segments) as f32,
+        }
+    }
+
+    pub fn index_of(generator: &dyn Generator, x: f32) -> i64 {
+        let mut index = (x / (SPACING * SAMPLES as f32)).floor() as i64;
+        while x < generator.x(index * SAMPLES) {
+            index -= 1;
+        }
+        while x >= generator.x((index + 1) * SAMPLES) {
+            index += 1;
+        }
+        index
+    }
+
+    pub fn first(&self) -> Point {
+        self.points[0]
+    }
+
+    pub fn last(&self) -> Point {
+        *self.points.last().unwrap()
+    }
+}
+
+pub struct ChunkCache {
+    capacity: usize,
+    clock: u64,
+    chunks: HashMap<i64, (u64, Chunk)>,
+    edited: HashSet<i64>,
+    /// Edited chunks that were evicted, handed back instead of generating
+    /// them again.
+    saved: HashMap<i64, Chunk>,
+}
+impl ChunkCache {
+    pub fn new(capacity: usize) -> ChunkCache {
+        assert!(capacity >= 2);
+        ChunkCache {
+            capacity,
+            clock: 0,
+            chunks: HashMap::new(),
+            edited: HashSet::new(),
+            saved: HashMap::new(),
+        }
+    }
+
+    pub fn get(&mut self, index: i64, generator: &dyn Generator) -> &mut Chunk {
+        if !self.chunks.contains_key(&index) {
+            let chunk = match self.saved.remove(&index) {
+                Some(chunk) => chunk,
+                None => Chunk::generate(generator, index),
+            };
+            self.insert(chunk);
+        }
+
+        self.clock += 1;
+        let entry = self.chunks.get_mut(&index).unwrap();
+        entry.0 = self.clock;
+        &mut entry.1
+    }
+
+    pub fn insert(&mut self, chunk: Chunk) {
+        while self.chunks.len() >= self.capacity && !self.chunks.contains_key(&chunk.index) {
+            let oldest = self
+                .chunks
+                .iter()
+                .min_by_key(|(_, (used, _))| *used)
+                .map(|(&index, _)| index)
+                .unwrap();
+            let (_, evicted) = self.chunks.remove(&oldest).unwrap();
+            if self.edited.contains(&oldest) {
+                self.saved.insert(oldest, evicted);
+            }
+        }
+
+        self.saved.remove(&chunk.index);
+        self.clock += 1;
+        self.chunks.insert(chunk.index, (self.clock, chunk));
+    }
+
+    /// Marks the chunk as changed from what its generator makes. Once evicted
+    /// it is saved aside rather than dropped, so generating it again cannot
+    /// undo the changes and how far other code wanders cannot change the land.
+    pub fn edit(&mut self, index: i64) {
+        self.edited.insert(index);
+    }
+
+    pub fn contains(&self, index: i64) -> bool {
+        self.chunks.contains_key(&index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Every chunk in the cache and every edited chunk saved aside.
+    pub fn chunks(&self) -> impl Iterator<Item = &Chunk> + '_ {
+        self.chunks
+            .values()
+            .map(|(_, chunk)| chunk)
+            .chain(self.saved.values())
+    }
+}
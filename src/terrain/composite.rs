@@ -0,0 +1,165 @@
+use crate::{
+    geom::{Line, Point, Vector},
+    terrain::{polyline::Polyline, Pad, Terrain},
+};
+
+pub struct Composite<T: Terrain> {
+    base: T,
+    sections: Vec<Polyline>,
+}
+impl<T: Terrain> Composite<T> {
+    pub fn new(base: T) -> Composite<T> {
+        Composite {
+            base,
+            sections: Vec::new(),
+        }
+    }
+
+    pub fn splice(&mut self, section: Polyline) {
+        assert!(self
+            .sections
+            .iter()
+            .all(|other| section.last().0 < other.first().0 || section.first().0 > other.last().0));
+        self.sections.push(section);
+    }
+
+    fn section_at(&self, x: f32) -> Option<&Polyline> {
+        self.sections.iter().find(|section| section.contains(x))
+    }
+
+    fn bridge(&self, mut line: Line, x: f32) -> Line {
+        for section in &self.sections {
+            let (first, last) = (section.first(), section.last());
+            if x < first.0 && line.1 .0 > first.0 {
+                line.1 = first;
+            }
+            if x > last.0 && line.0 .0 < last.0 {
+                line.0 = last;
+            }
+        }
+        line
+    }
+
+    fn clip(&self, line: Line) -> Vec<Line> {
+        let mut pieces = vec![line];
+        for section in &self.sections {
+            let (first, last) = (section.first(), section.last());
+            pieces = pieces
+                .into_iter()
+                .flat_map(|piece| {
+                    if piece.1 .0 <= first.0 || piece.0 .0 >= last.0 {
+                        return vec![piece];
+                    }
+                    let mut outside = vec![];
+                    if piece.0 .0 < first.0 {
+                        outside.push(Line(piece.0, first));
+                    }
+                    if piece.1 .0 > last.0 {
+                        outside.push(Line(last, piece.1));
+                    }
+                    outside
+                })
+                .collect();
+        }
+        pieces
+    }
+}
+impl<T: Terrain> Terrain for Composite<T> {
+    fn get(&mut self, pos: Point) -> Line {
+        if let Some(section) = self.section_at(pos.0) {
+            return section.line_at(pos.0);
+        }
+
+        let line = self.base.get(pos);
+        self.bridge(line, pos.0)
+    }
+
+    fn segments(&mut self, min: f32, max: f32) -> Vec<Line> {
+        let mut lines: Vec<Line> = self
+            .base
+            .segments(min, max)
+            .into_iter()
+            .flat_map(|line| self.clip(line))
+            .collect();
+        for section in &self.sections {
+            lines.extend(
+                section
+                    .all()
+                    .filter(|line| line.1 .0 >= min && line.0 .0 <= max),
+            );
+        }
+        lines.sort_by(|a, b| a.0 .0.partial_cmp(&b.0 .0).unwrap());
+        lines
+    }
+
+    fn gravity(&self, pos: Point) -> Vector {
+        self.base.gravity(pos)
+    }
+
+    fn deform(&mut self, center: Point, radius: f32) {
+        self.base.deform(center, radius);
+    }
+
+    fn pads(&mut self, min: f32, max: f32) -> Vec<Pad> {
+        let mut pads = self.base.pads(min, max);
+        pads.retain(|pad| {
+            self.sections
+                .iter()
+                .all(|section| pad.right() < section.first().0 || pad.left() > section.last().0)
+        });
+        pads
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn composite() -> Composite<Polyline> {
+        let flat = Polyline::new(vec![Point(-100.0, 0.0), Point(100.0, 0.0)]);
+        let mut composite = Composite::new(flat);
+        composite.splice(Polyline::new(vec![
+            Point(-10.0, 20.0),
+            Point(0.0, 30.0),
+            Point(10.0, 20.0),
+        ]));
+        composite
+    }
+
+    #[test]
+    fn sections_replace_the_base() {
+        let mut land = composite();
+        assert_eq!(land.height(-5.0), 25.0);
+        assert_eq!(land.height(5.0), 25.0);
+        assert!((land.height(-55.0) - 10.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn base_is_bridged_onto_sections() {
+        let mut land = composite();
+        assert_eq!(land.get(Point(-50.0, 0.0)).1, Point(-10.0, 20.0));
+        assert_eq!(land.get(Point(50.0, 0.0)).0, Point(10.0, 20.0));
+    }
+
+    #[test]
+    fn segments_clip_the_base_around_sections() {
+        let mut land = composite();
+        let segments = land.segments(-100.0, 100.0);
+        assert_eq!(
+            segments,
+            vec![
+                Line(Point(-100.0, 0.0), Point(-10.0, 20.0)),
+                Line(Point(-10.0, 20.0), Point(0.0, 30.0)),
+                Line(Point(0.0, 30.0), Point(10.0, 20.0)),
+                Line(Point(10.0, 20.0), Point(100.0, 0.0)),
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn overlapping_sections_are_refused() {
+        let mut land = composite();
+        land.splice(Polyline::new(vec![Point(5.0, 0.0), Point(15.0, 0.0)]));
+    }
+}
@@ -0,0 +1,366 @@
+use crate::{geom::Point, math};
+
+pub trait Generator {
+    fn point(&self, index: i64) -> Point;
+
+    fn x(&self, index: i64) -> f32 {
+        self.point(index).0
+    }
+}
+
+pub fn hash(seed: u64, index: i64) -> u64 {
+    let mut z = seed ^ (index as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+pub fn random(seed: u64, index: i64) -> f32 {
+    (hash(seed, index) >> 40) as f32 / (1u64 << 24) as f32
+}
+
+pub fn random_signed(seed: u64, index: i64) -> f32 {
+    random(seed, index) * 2.0 - 1.0
+}
+
+pub const SPACING: f32 = 20.0;
+
+pub fn sample_x(seed: u64, index: i64) -> f32 {
+    let jitter = random(seed ^ 0x5851_f42d_4c95_7f2d, index) - 0.5;
+    (index as f32 + jitter * 0.5) * SPACING
+}
+
+/// The original land: every point 10 to 30 further out and up to 30 higher or
+/// lower than the one before, walking both ways from `start`.
+pub struct RandomWalk {
+    pub seed: u64,
+    pub start: Point,
+}
+impl RandomWalk {
+    pub fn new(seed: u64) -> RandomWalk {
+        RandomWalk {
+            seed,
+            start: Point(0.0, -30.0),
+        }
+    }
+
+    fn step(&self, index: i64) -> Point {
+        Point(
+            10.0 + random(self.seed, index) * 20.0,
+            random_signed(self.seed ^ 0x2545_f491_4f6c_dd1d, index) * 30.0,
+        )
+    }
+}
+impl Generator for RandomWalk {
+    fn point(&self, index: i64) -> Point {
+        if index >= 0 {
+            (1..=index).fold(self.start, |point, index| point + self.step(index))
+        } else {
+            (index..0).fold(self.start, |point, index| {
+                let step = self.step(index);
+                Point(point.0 - step.0, point.1 + step.1)
+            })
+        }
+    }
+}
+
+pub struct MidpointDisplacement {
+    pub seed: u64,
+    pub levels: u32,
+    pub amplitude: f32,
+    pub roughness: f32,
+    pub base: f32,
+}
+impl MidpointDisplacement {
+    pub fn new(seed: u64) -> MidpointDisplacement {
+        MidpointDisplacement {
+            seed,
+            levels: 8,
+            amplitude: 200.0,
+            roughness: std::f32::consts::FRAC_1_SQRT_2,
+            base: -30.0,
+        }
+    }
+
+    fn anchor(&self, index: i64) -> f32 {
+        if index == 0 {
+            return self.base;
+        }
+        self.base + random_signed(self.seed, index) * self.amplitude * 2.0
+    }
+
+    fn height(&self, index: i64) -> f32 {
+        let span = 1i64 << self.levels;
+        let mut lo = index.div_euclid(span) * span;
+        let mut hi = lo + span;
+        let mut lo_height = self.anchor(lo);
+        let mut hi_height = self.anchor(hi);
+        let mut amplitude = self.amplitude;
+
+        while lo != index {
+            let mid = (lo + hi) / 2;
+            let displacement = random_signed(self.seed ^ 0x2545_f491_4f6c_dd1d, mid) * amplitude;
+            let mid_height = (lo_height + hi_height) * 0.5 + displacement;
+            if index < mid {
+                hi = mid;
+                hi_height = mid_height;
+            } else {
+                lo = mid;
+                lo_height = mid_height;
+            }
+            amplitude *= self.roughness;
+        }
+
+        lo_height
+    }
+}
+impl Generator for MidpointDisplacement {
+    fn point(&self, index: i64) -> Point {
+        Point(sample_x(self.seed, index), self.height(index))
+    }
+
+    fn x(&self, index: i64) -> f32 {
+        sample_x(self.seed, index)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoiseKind {
+    Value,
+    Perlin,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Noise {
+    pub seed: u64,
+    pub kind: NoiseKind,
+    pub octaves: u32,
+    pub wavelength: f32,
+    pub amplitude: f32,
+    pub roughness: f32,
+    pub base: f32,
+}
+impl Noise {
+    pub fn new(seed: u64) -> Noise {
+        Noise {
+            seed,
+            kind: NoiseKind::Perlin,
+            octaves: 5,
+            wavelength: 800.0,
+            amplitude: 200.0,
+            roughness: 0.5,
+            base: -30.0,
+        }
+    }
+
+    pub fn sample(&self, x: f32) -> f32 {
+        let mut total = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0 / self.wavelength;
+        for octave in 0..self.octaves {
+            let seed = hash(self.seed, octave as i64);
+            let noise = match self.kind {
+                NoiseKind::Value => value_noise(seed, x * frequency),
+                NoiseKind::Perlin => perlin_noise(seed, x * frequency),
+            };
+            total += noise * amplitude;
+            amplitude *= self.roughness;
+            frequency *= 2.0;
+        }
+        total
+    }
+
+    pub fn height(&self, x: f32) -> f32 {
+        self.base + self.sample(x) * self.amplitude
+    }
+}
+impl Generator for Noise {
+    fn point(&self, index: i64) -> Point {
+        let x = sample_x(self.seed, index);
+        Point(x, self.height(x))
+    }
+
+    fn x(&self, index: i64) -> f32 {
+        sample_x(self.seed, index)
+    }
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+pub fn value_noise(seed: u64, x: f32) -> f32 {
+    let cell = x.floor();
+    let t = fade(x - cell);
+    let a = random_signed(seed, cell as i64);
+    let b = random_signed(seed, cell as i64 + 1);
+    a + (b - a) * t
+}
+
+pub fn perlin_noise(seed: u64, x: f32) -> f32 {
+    let cell = x.floor();
+    let offset = x - cell;
+    let t = fade(offset);
+    let a = random_signed(seed, cell as i64) * offset;
+    let b = random_signed(seed, cell as i64 + 1) * (offset - 1.0);
+    (a + (b - a) * t) * 2.0
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Biome {
+    Plains,
+    Canyons,
+    Mountains,
+}
+impl Biome {
+    fn height(self, seed: u64, x: f32) -> f32 {
+        match self {
+            Biome::Plains => Noise {
+                octaves: 3,
+                wavelength: 600.0,
+                amplitude: 20.0,
+                roughness: 0.4,
+                ..Noise::new(seed)
+            }
+            .height(x),
+            Biome::Canyons => {
+                let plateau = Noise {
+                    octaves: 3,
+                    wavelength: 400.0,
+                    amplitude: 30.0,
+                    ..Noise::new(seed)
+                };
+                let cuts = Noise {
+                    kind: NoiseKind::Value,
+                    octaves: 2,
+                    wavelength: 500.0,
+                    amplitude: 1.0,
+                    roughness: 0.3,
+                    ..Noise::new(hash(seed, -1))
+                };
+                let cut = (1.0 - cuts.sample(x).abs() * 5.0).max(0.0);
+                plateau.height(x) - math::sqrt(cut) * 250.0
+            }
+            Biome::Mountains => {
+                let ridges = Noise {
+                    octaves: 6,
+                    wavelength: 700.0,
+                    amplitude: 1.0,
+                    roughness: 0.55,
+                    ..Noise::new(seed)
+                };
+                -30.0 + (1.0 - ridges.sample(x).abs()) * 400.0
+            }
+        }
+    }
+}
+
+pub struct Biomes {
+    pub seed: u64,
+    pub width: f32,
+    pub blend: f32,
+    pub biomes: Vec<Biome>,
+}
+impl Biomes {
+    pub fn new(seed: u64) -> Biomes {
+        Biomes {
+            seed,
+            width: 3000.0,
+            blend: 400.0,
+            biomes: vec![Biome::Plains, Biome::Canyons, Biome::Mountains],
+        }
+    }
+
+    pub fn biome(&self, x: f32) -> Biome {
+        self.region(self.region_of(x))
+    }
+
+    fn region_of(&self, x: f32) -> i64 {
+        (x / self.width).floor() as i64
+    }
+
+    fn region(&self, region: i64) -> Biome {
+        let idx = hash(self.seed, region) % (self.biomes.len() as u64);
+        self.biomes[idx as usize]
+    }
+
+    pub fn height(&self, x: f32) -> f32 {
+        let region = self.region_of(x);
+        let offset = x - (region as f32) * self.width;
+        let height = self.region(region).height(self.seed, x);
+
+        let (neighbor, distance) = if offset < self.width * 0.5 {
+            (region - 1, offset)
+        } else {
+            (region + 1, self.width - offset)
+        };
+        if distance >= self.blend {
+            return height;
+        }
+
+        let neighbor_height = self.region(neighbor).height(self.seed, x);
+        let t = fade(0.5 + distance / self.blend * 0.5);
+        neighbor_height + (height - neighbor_height) * t
+    }
+}
+impl Generator for Biomes {
+    fn point(&self, index: i64) -> Point {
+        let x = sample_x(self.seed, index);
+        Point(x, self.height(x))
+    }
+
+    fn x(&self, index: i64) -> f32 {
+        sample_x(self.seed, index)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn generators(seed: u64) -> Vec<Box<dyn Generator>> {
+        vec![
+            Box::new(RandomWalk::new(seed)),
+            Box::new(MidpointDisplacement::new(seed)),
+            Box::new(Noise::new(seed)),
+            Box::new(Noise {
+                kind: NoiseKind::Value,
+                ..Noise::new(seed)
+            }),
+            Box::new(Biomes::new(seed)),
+        ]
+    }
+
+    #[test]
+    fn same_seed_same_points() {
+        for (a, b) in generators(42).iter().zip(generators(42).iter()) {
+            for index in -500..500 {
+                let (a, b) = (a.point(index), b.point(index));
+                assert_eq!((a.0, a.1), (b.0, b.1));
+            }
+        }
+    }
+
+    #[test]
+    fn points_are_sorted_and_spaced() {
+        for generator in generators(7) {
+            for index in -500..500 {
+                let a = generator.point(index);
+                let b = generator.point(index + 1);
+                let spacing = b.0 - a.0;
+                assert!((10.0..=30.0).contains(&spacing), "{}", spacing);
+                assert!(a.1.is_finite());
+            }
+        }
+    }
+
+    #[test]
+    fn random_walk_climbs_like_the_original() {
+        let walk = RandomWalk::new(3);
+        assert_eq!(walk.point(0), walk.start);
+        for index in -500..500 {
+            let (a, b) = (walk.point(index), walk.point(index + 1));
+            assert!((b.1 - a.1).abs() <= 30.0);
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use crate::{
+    geom::{Line, Point},
+    math,
+    terrain::{
+        chunk::{Chunk, ChunkCache},
+        generator::{Generator, RandomWalk},
+        Pad, Terrain,
+    },
+};
+
+pub struct Land {
+    generator: Box<dyn Generator>,
+    chunks: ChunkCache,
+}
+impl Default for Land {
+    fn default() -> Land {
+        Land::new()
+    }
+}
+impl Land {
+    /// Land walking randomly like the original, with a random seed. Other
+    /// generators go through `with_generator`.
+    pub fn new() -> Land {
+        Self::with_generator(Box::new(RandomWalk::new(rand::random())))
+    }
+
+    pub fn with_generator(generator: Box<dyn Generator>) -> Land {
+        Land {
+            generator,
+            chunks: ChunkCache::new(32),
+        }
+    }
+
+    pub fn save(&self) -> Vec<Chunk> {
+        let mut chunks: Vec<Chunk> = self.chunks.chunks().cloned().collect();
+        chunks.sort_by_key(|chunk| chunk.index);
+        chunks
+    }
+
+    pub fn restore(&mut self, chunks: Vec<Chunk>) {
+        chunks.into_iter().for_each(|chunk| {
+            self.chunks.edit(chunk.index);
+            self.chunks.insert(chunk);
+        });
+    }
+
+    fn chunk(&mut self, index: i64) -> &mut Chunk {
+        self.chunks.get(index, self.generator.as_ref())
+    }
+
+    fn chunk_of(&self, x: f32) -> i64 {
+        Chunk::index_of(self.generator.as_ref(), x)
+    }
+
+    fn segment(&mut self, x: f32) -> Line {
+        let index = self.chunk_of(x);
+        let next = self.chunk(index + 1).first();
+        let points = &self.chunk(index).points;
+
+        let idx = points.partition_point(|point| point.0 < x).max(1);
+        if idx == points.len() {
+            Line(points[idx - 1], next)
+        } else {
+            Line(points[idx - 1], points[idx])
+        }
+    }
+
+    fn points(&mut self, min: f32, max: f32) -> Vec<Point> {
+        let first = self.chunk_of(min);
+        let last = self.chunk_of(max);
+        let mut points = Vec::new();
+        for index in first..=last {
+            points.extend_from_slice(&self.chunk(index).points);
+        }
+        points.push(self.chunk(last + 1).first());
+        points
+    }
+}
+impl Terrain for Land {
+    fn get(&mut self, pos: Point) -> Line {
+        self.segment(pos.0)
+    }
+
+    fn segments(&mut self, min: f32, max: f32) -> Vec<Line> {
+        self.points(min, max)
+            .windows(2)
+            .map(|pair| Line(pair[0], pair[1]))
+            .filter(|line| line.1 .0 >= min && line.0 .0 <= max)
+            .collect()
+    }
+
+    fn deform(&mut self, center: Point, radius: f32) {
+        let min = center.0 - radius;
+        let max = center.0 + radius;
+        for index in self.chunk_of(min)..=self.chunk_of(max) {
+            self.chunks.edit(index);
+        }
+
+        let samples = 8;
+        for sample in 0..=samples {
+            let x = min + (max - min) * (sample as f32) / (samples as f32);
+            let ground = self.segment(x);
+            if (ground.0 .0 - x).abs() < 0.5 || (ground.1 .0 - x).abs() < 0.5 {
+                continue;
+            }
+
+            let index = self.chunk_of(x);
+            let points = &mut self.chunk(index).points;
+            let idx = points.partition_point(|point| point.0 < x);
+            points.insert(idx, ground.at_x(x));
+        }
+
+        for index in self.chunk_of(min)..=self.chunk_of(max) {
+            let chunk = self.chunk(index);
+            chunk
+                .pads
+                .retain(|pad| pad.right() < min || pad.left() > max);
+            chunk
+                .points
+                .iter_mut()
+                .filter(|point| point.0 > min && point.0 < max)
+                .for_each(|point| {
+                    let dx = point.0 - center.0;
+                    let crater = center.1 - math::sqrt(radius * radius - dx * dx);
+                    point.1 = point.1.min(crater);
+                });
+        }
+    }
+
+    fn pads(&mut self, min: f32, max: f32) -> Vec<Pad> {
+        let mut pads = Vec::new();
+        for index in self.chunk_of(min)..=self.chunk_of(max) {
+            pads.extend(
+                self.chunk(index)
+                    .pads
+                    .iter()
+                    .filter(|pad| pad.right() >= min && pad.left() <= max),
+            );
+        }
+        pads
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::terrain::generator::MidpointDisplacement;
+
+    fn land() -> Land {
+        Land::with_generator(Box::new(MidpointDisplacement::new(11)))
+    }
+
+    fn heights(land: &mut Land) -> Vec<(f32, f32)> {
+        land.segments(-300.0, 300.0)
+            .iter()
+            .map(|line| (line.0 .0, line.0 .1))
+            .collect()
+    }
+
+    #[test]
+    fn same_ground_after_flying_away() {
+        let mut land = land();
+        let before = heights(&mut land);
+        for step in 0..2000 {
+            land.get(Point(step as f32 * 50.0, 0.0));
+        }
+        assert!(!land.chunks.contains(0));
+        assert_eq!(heights(&mut land), before);
+    }
+
+    #[test]
+    fn deformation_is_kept_after_flying_away() {
+        let mut land = land();
+        let ground = land.get(Point(0.0, 0.0)).at_x(0.0);
+        land.deform(ground, 10.0);
+        let deformed = heights(&mut land);
+        for step in 0..2000 {
+            land.get(Point(step as f32 * 50.0, 0.0));
+        }
+        assert!(!land.chunks.contains(0));
+        assert_eq!(heights(&mut land), deformed);
+    }
+
+    #[test]
+    fn crater_looks_the_same_through_any_window() {
+        let mut land = land();
+        let ground = land.get(Point(0.0, 0.0)).at_x(0.0);
+        land.deform(ground, 10.0);
+        let bottom = land.height(0.0);
+
+        let narrow = land.segments(-5.0, 5.0);
+        let wide = land.segments(-2000.0, 2000.0);
+        assert!(narrow.iter().all(|line| wide.contains(line)));
+        assert!(narrow
+            .iter()
+            .any(|line| line.0 .0 < 0.0 && line.1 .0 >= 0.0));
+        assert_eq!(land.get(Point(0.0, 500.0)), land.get(Point(0.0, -500.0)));
+
+        for step in 0..2000 {
+            land.get(Point(step as f32 * -50.0, 0.0));
+        }
+        assert_eq!(land.height(0.0), bottom);
+        assert_eq!(land.segments(-5.0, 5.0), narrow);
+    }
+
+    #[test]
+    fn deformation_survives_save_and_restore() {
+        let mut land = land();
+        let ground = land.get(Point(0.0, 0.0)).at_x(0.0);
+        land.deform(ground, 10.0);
+        let deformed = heights(&mut land);
+
+        let mut restored = Land::with_generator(Box::new(MidpointDisplacement::new(11)));
+        restored.restore(land.save());
+        assert_eq!(heights(&mut restored), deformed);
+        assert!(land.height(0.0) < ground.1 - 9.0);
+    }
+
+    #[test]
+    fn craters_along_a_long_flight_keep_the_cache_bounded() {
+        let mut land = land();
+        let mut craters = Vec::new();
+        for step in 0..200 {
+            let x = step as f32 * 100.0;
+            let ground = land.get(Point(x, 0.0)).at_x(x);
+            land.deform(ground, 10.0);
+            craters.push((x, land.height(x)));
+        }
+        assert!(land.chunks.len() <= 32);
+        assert!(!land.chunks.contains(0));
+        for (x, height) in craters {
+            assert_eq!(land.height(x), height);
+        }
+    }
+}
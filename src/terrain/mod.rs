@@ -0,0 +1,95 @@
+pub mod cave;
+pub mod chunk;
+pub mod composite;
+pub mod generator;
+pub mod land;
+pub mod polyline;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    geom::{Line, Mat3, Point, Vector},
+    inertia::Inertia,
+    ship::Ship,
+};
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Pad {
+    pub center: Point,
+    pub width: f32,
+    pub multiplier: f32,
+}
+impl Pad {
+    pub fn left(&self) -> f32 {
+        self.center.0 - self.width * 0.5
+    }
+
+    pub fn right(&self) -> f32 {
+        self.center.0 + self.width * 0.5
+    }
+
+    pub fn contains(&self, x: f32) -> bool {
+        x >= self.left() && x <= self.right()
+    }
+
+    pub fn line(&self) -> Line {
+        Line(
+            Point(self.left(), self.center.1),
+            Point(self.right(), self.center.1),
+        )
+    }
+}
+
+pub trait Terrain {
+    fn get(&mut self, pos: Point) -> Line;
+    fn segments(&mut self, min: f32, max: f32) -> Vec<Line>;
+
+    fn height(&mut self, x: f32) -> f32 {
+        self.get(Point(x, 0.0)).at_x(x).1
+    }
+
+    fn gravity(&self, _pos: Point) -> Vector {
+        Ship::gravity()
+    }
+
+    fn apply_gravity(&mut self, point: &mut Inertia) {
+        point.force(self.gravity(point.position));
+    }
+
+    fn handle_collision(&mut self, pos: &mut Point) {
+        let ground_line = self.get(*pos);
+        let ground_hit = ground_line.projection(*pos);
+        if pos.1 < ground_hit.1 {
+            *pos = ground_hit;
+        }
+    }
+
+    fn deform(&mut self, _center: Point, _radius: f32) {}
+
+    fn pads(&mut self, _min: f32, _max: f32) -> Vec<Pad> {
+        Vec::new()
+    }
+
+    fn pad(&mut self, x: f32) -> Option<Pad> {
+        self.pads(x, x).into_iter().find(|pad| pad.contains(x))
+    }
+}
+
+pub fn drawable(segments: Vec<Line>) -> impl Iterator<Item = Mat3> {
+    segments.into_iter().map(|line| {
+        let pos = line.center();
+        let direction = line.direction().rot90() * -1.0;
+
+        Mat3::translate(pos.0, pos.1)
+            * Mat3::rotate_y_to(direction)
+            * Mat3::scale(line.len() * 0.52, -1.0)
+    })
+}
+
+pub fn pads_drawable(pads: Vec<Pad>) -> impl Iterator<Item = Mat3> {
+    drawable(
+        pads.iter()
+            .map(|pad| pad.line() + Point(0.0, 1.0))
+            .collect(),
+    )
+}
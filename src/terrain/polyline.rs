@@ -0,0 +1,81 @@
+use crate::{
+    geom::{Line, Point},
+    terrain::Terrain,
+};
+
+pub struct Polyline {
+    points: Vec<Point>,
+}
+impl Polyline {
+    pub fn new(points: Vec<Point>) -> Polyline {
+        assert!(points.len() >= 2);
+        assert!(points.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        Polyline { points }
+    }
+
+    pub fn first(&self) -> Point {
+        self.points[0]
+    }
+
+    pub fn last(&self) -> Point {
+        *self.points.last().unwrap()
+    }
+
+    pub fn contains(&self, x: f32) -> bool {
+        x >= self.first().0 && x <= self.last().0
+    }
+
+    pub fn all(&self) -> impl Iterator<Item = Line> + '_ {
+        self.points.windows(2).map(|pair| Line(pair[0], pair[1]))
+    }
+
+    pub fn line_at(&self, x: f32) -> Line {
+        let idx = self.points.partition_point(|point| point.0 < x);
+        let idx = idx.clamp(1, self.points.len() - 1);
+        Line(self.points[idx - 1], self.points[idx])
+    }
+}
+impl Terrain for Polyline {
+    fn get(&mut self, pos: Point) -> Line {
+        self.line_at(pos.0)
+    }
+
+    fn segments(&mut self, min: f32, max: f32) -> Vec<Line> {
+        self.all()
+            .filter(|line| line.1 .0 >= min && line.0 .0 <= max)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn hill() -> Polyline {
+        Polyline::new(vec![Point(0.0, 0.0), Point(10.0, 10.0), Point(20.0, 0.0)])
+    }
+
+    #[test]
+    fn height_follows_the_segment_under_x() {
+        let mut hill = hill();
+        assert_eq!(hill.height(5.0), 5.0);
+        assert_eq!(hill.height(15.0), 5.0);
+        assert_eq!(
+            hill.get(Point(10.0, 0.0)),
+            Line(Point(0.0, 0.0), Point(10.0, 10.0))
+        );
+        assert_eq!(hill.get(Point(-5.0, 0.0)), hill.all().next().unwrap());
+        assert_eq!(hill.get(Point(25.0, 0.0)), hill.all().last().unwrap());
+    }
+
+    #[test]
+    fn segments_overlapping_the_range() {
+        let mut hill = hill();
+        assert_eq!(
+            hill.segments(12.0, 30.0),
+            vec![Line(Point(10.0, 10.0), Point(20.0, 0.0))]
+        );
+        assert_eq!(hill.segments(-10.0, 30.0).len(), 2);
+        assert!(hill.segments(21.0, 30.0).is_empty());
+    }
+}
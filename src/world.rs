@@ -1,21 +1,38 @@
 use instant::{Duration, Instant};
 
 use crate::{
+    camera::Camera,
+    game::{Game, ShipId, State},
+    geom::{Mat3, Point},
     inertia::Inertia,
+    input::{Event, InputMap},
+    net::{Lockstep, Message},
     render::{
-        render_target::RenderTarget,
-        scene::{Drawable, Scene},
+        render_target::{RenderTarget, Viewport},
+        scene::{Color, Drawable, Scene, Tinted},
     },
-    ship::{Land, Ship, Throttle},
+    ship::{Ship, Telemetry, Throttle},
+    sling::Sling,
+    terrain::{self, Pad, Terrain},
 };
 
 struct IntegrationController {
     clock: Instant,
+    lag: Duration,
+    paused: bool,
+    scale: f32,
+    max_steps: u32,
+    dropped: Duration,
 }
 impl IntegrationController {
     fn new() -> IntegrationController {
         IntegrationController {
             clock: Instant::now(),
+            lag: Duration::from_secs(0),
+            paused: false,
+            scale: 1.0,
+            max_steps: 25,
+            dropped: Duration::from_secs(0),
         }
     }
 
@@ -23,13 +40,84 @@ impl IntegrationController {
         Duration::from_millis((Inertia::step() * 1000.0) as u64)
     }
 
-    fn integrate(&mut self, ship: &mut Ship, land: &mut Land) -> bool {
+    fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    fn resume(&mut self) {
+        if self.paused {
+            self.paused = false;
+            self.clock = Instant::now();
+        }
+    }
+
+    /// Fastest the simulation may run against the clock.
+    fn max_scale() -> f32 {
+        16.0
+    }
+
+    /// Keeps the scale between stopped and `max_scale`, ignoring NaN.
+    fn set_scale(&mut self, scale: f32) {
+        if !scale.is_nan() {
+            self.scale = scale.max(0.0).min(Self::max_scale());
+        }
+    }
+
+    fn integrate<F: FnMut() -> bool>(&mut self, tick: F) -> f32 {
         let now = Instant::now();
-        while self.clock < now {
-            ship.integrate(land);
-            self.clock += Self::step();
+        let elapsed = now - self.clock;
+        self.clock = now;
+        self.catch_up(elapsed, tick)
+    }
+
+    /// Ticks through `elapsed` time, returning how far into the next tick
+    /// it got. A tick returning false stalled, and its time is kept for the
+    /// next call.
+    fn catch_up<F: FnMut() -> bool>(&mut self, elapsed: Duration, mut tick: F) -> f32 {
+        if !self.paused {
+            self.lag += elapsed.mul_f32(self.scale);
+        }
+
+        let mut steps = 0;
+        while self.lag >= Self::step() {
+            if steps == self.max_steps {
+                let remainder = (self.lag.as_nanos() % Self::step().as_nanos()) as u64;
+                let remainder = Duration::from_nanos(remainder);
+                self.dropped += self.lag - remainder;
+                self.lag = remainder;
+                break;
+            }
+
+            if !tick() {
+                break;
+            }
+            self.lag -= Self::step();
+            steps += 1;
+        }
+        (self.lag.as_secs_f32() / Self::step().as_secs_f32()).min(1.0)
+    }
+}
+
+/// One player's share of the screen, following their ship with its own
+/// camera.
+struct View<T>
+where
+    T: RenderTarget,
+{
+    ship: ShipId,
+    camera: Camera,
+    scene: T::RenderScene<Scene>,
+}
+impl<T> View<T>
+where
+    T: RenderTarget,
+{
+    fn new(target: &mut T, ship: ShipId) -> View<T> {
+        View {
+            ship,
+            camera: Camera::new(),
+            scene: target.new_scene(Scene::new()),
         }
-        true
     }
 }
 
@@ -38,27 +126,19 @@ where
     T: RenderTarget,
 {
     target: T,
-    scene: T::RenderScene<Scene>,
-    ship: Ship,
-    land: Land,
+    views: Vec<View<T>>,
+    game: Game,
+    network: Option<Lockstep>,
+    input: InputMap,
     integration: IntegrationController,
-    prev_zoom: f32,
+    frame: Instant,
 }
 impl<T> From<T> for World<T>
 where
     T: RenderTarget,
 {
-    fn from(mut target: T) -> Self {
-        let scene = target.new_scene(Scene::new());
-
-        World {
-            target,
-            scene,
-            ship: Ship::new(),
-            land: Land::new(),
-            integration: IntegrationController::new(),
-            prev_zoom: 0.0002,
-        }
+    fn from(target: T) -> Self {
+        World::with_game(target, Game::with_seed(rand::random()))
     }
 }
 #[cfg(feature = "wgpu_render")]
@@ -73,33 +153,380 @@ impl<T> World<T>
 where
     T: RenderTarget,
 {
+    pub fn with_terrain(target: T, land: Box<dyn Terrain>) -> World<T> {
+        World::with_game(target, Game::new(land))
+    }
+
+    pub fn with_game(mut target: T, game: Game) -> World<T> {
+        let views = vec![View::new(&mut target, ShipId::PLAYER)];
+
+        World {
+            target,
+            views,
+            game,
+            network: None,
+            input: InputMap::default(),
+            integration: IntegrationController::new(),
+            frame: Instant::now(),
+        }
+    }
+
+    /// Plays a networked session, stepping only as far as the relay allows
+    /// and following the ship the relay gave us.
+    pub fn with_lockstep(target: T, lockstep: Lockstep, game: Game) -> World<T> {
+        let mut world = World::with_game(target, game);
+        world.views[0].ship = lockstep.ship();
+        world.network = Some(lockstep);
+        world
+    }
+
+    /// Ship flown by the local keyboard.
+    pub fn local_ship(&self) -> ShipId {
+        self.network
+            .as_ref()
+            .map_or(ShipId::PLAYER, |lockstep| lockstep.ship())
+    }
+
     pub fn resize(&mut self, width: u32, height: u32) {
         self.target.resize(width, height);
     }
 
+    /// Camera of the first view, which follows the player.
+    pub fn camera(&mut self) -> &mut Camera {
+        &mut self.views[0].camera
+    }
+
+    pub fn view_camera(&mut self, ship: ShipId) -> Option<&mut Camera> {
+        self.views
+            .iter_mut()
+            .find(|view| view.ship == ship)
+            .map(|view| &mut view.camera)
+    }
+
+    /// Splits the screen once more to follow `ship`.
+    pub fn add_view(&mut self, ship: ShipId) {
+        let view = View::new(&mut self.target, ship);
+        self.views.push(view);
+    }
+
+    /// Removes the view following `ship`, as long as another view remains.
+    pub fn remove_view(&mut self, ship: ShipId) -> bool {
+        match self.views.iter().position(|view| view.ship == ship) {
+            Some(index) if self.views.len() > 1 => {
+                self.views.remove(index);
+                true
+            }
+            _ => false,
+        }
+    }
+
     pub fn redraw(&mut self) {
-        let origin = self.ship.origin();
-        let ground = self.land.get(origin);
-        let ground = ground.0 + ground.direction() * (origin - ground.0).0;
-        let distance = (origin - ground).len() + 30.0;
-        let zoom = (1.0 / distance).min(0.02);
-        let zoom = zoom * 0.01 + self.prev_zoom * 0.99;
-        self.prev_zoom = zoom;
-
-        let size = self.target.get_size();
-
-        self.integration.integrate(&mut self.ship, &mut self.land);
-        self.scene.set_camera(self.ship.origin(), zoom, size);
-
-        let mut draw: [&mut dyn Drawable; 3] = [
-            &mut self.ship.drawable(),
-            &mut self.ship.active_throttles(),
-            &mut self.land.drawable(),
+        let now = Instant::now();
+        let dt = (now - self.frame).as_secs_f32();
+        self.frame = now;
+
+        let viewports = Viewport::columns(self.target.get_size(), self.views.len() as u32);
+
+        let (game, views, network) = (&mut self.game, &mut self.views, &mut self.network);
+        let alpha = self.integration.integrate(|| {
+            let impacts = match network {
+                Some(lockstep) if lockstep.stalled() => return false,
+                Some(lockstep) => lockstep.advance(game),
+                None => game.advance(),
+            };
+            for (ship, impact) in impacts {
+                views
+                    .iter_mut()
+                    .filter(|view| view.ship == ship)
+                    .for_each(|view| view.camera.shake((impact.speed() - 5.0).max(0.0) * 0.2));
+            }
+            true
+        });
+
+        let tick = self.game.tick();
+        let ghosts: Vec<(Ship, Option<Sling>)> = self
+            .game
+            .ghosts()
+            .filter(|ghost| ghost.visible(tick))
+            .map(|ghost| {
+                let pilot = ghost.pilot();
+                let sling = pilot.sling().map(|sling| sling.interpolated(alpha));
+                (pilot.ship().interpolated(alpha), sling)
+            })
+            .collect();
+        let ships: Vec<(Ship, Option<Sling>, State)> = self
+            .game
+            .pilots()
+            .filter(|(_, pilot)| pilot.state().ship_visible())
+            .map(|(_, pilot)| {
+                let sling = pilot.sling().map(|sling| sling.interpolated(alpha));
+                (pilot.ship().interpolated(alpha), sling, pilot.state())
+            })
+            .collect();
+        let targets: Vec<Pad> = self
+            .game
+            .pilots()
+            .filter_map(|(_, pilot)| pilot.target())
+            .collect();
+
+        let mut layers = Vec::with_capacity(self.views.len());
+        let game = &mut self.game;
+        for (view, viewport) in self.views.iter_mut().zip(&viewports) {
+            let pilot = game.pilot(view.ship).unwrap_or_else(|| game.player());
+            let ship = pilot.ship().interpolated(alpha);
+            view.camera.update(dt, &ship, game.land());
+            view.scene
+                .set_camera(view.camera.transform(viewport.size()));
+
+            let center = view.camera.position();
+            let radius = view.camera.view_radius(viewport.size());
+            layers.push(Self::layer(
+                game.land(),
+                (center.0 - radius, center.0 + radius),
+                &ghosts,
+                &ships,
+                &targets,
+            ));
+        }
+
+        let mut draws: Vec<Vec<&mut dyn Drawable>> = layers
+            .iter_mut()
+            .map(|layer| {
+                layer
+                    .iter_mut()
+                    .map(|part| part.as_mut() as &mut dyn Drawable)
+                    .collect()
+            })
+            .collect();
+        let views = self
+            .views
+            .iter_mut()
+            .zip(viewports)
+            .zip(draws.iter_mut())
+            .map(|((view, viewport), draw)| (viewport, &mut view.scene, &mut draw[..]))
+            .collect();
+        self.target.render_viewports(views);
+    }
+
+    /// Everything visible between `range` for a single view.
+    fn layer<'a>(
+        land: &mut dyn Terrain,
+        range: (f32, f32),
+        ghosts: &'a [(Ship, Option<Sling>)],
+        ships: &'a [(Ship, Option<Sling>, State)],
+        targets: &[Pad],
+    ) -> Vec<Box<dyn Drawable + 'a>> {
+        let mut parts: Vec<Box<dyn Drawable + 'a>> = vec![
+            Box::new(terrain::drawable(land.segments(range.0, range.1))),
+            Box::new(terrain::pads_drawable(land.pads(range.0, range.1))),
         ];
-        self.target.render_one(&mut self.scene, &mut draw[..]);
+
+        // Arrows hanging over the pads cargo is due on.
+        let arrows: Vec<Mat3> = targets
+            .iter()
+            .map(|pad| pad.center)
+            .map(|Point(x, y)| {
+                Mat3::triangle(
+                    Point(x - 3.0, y + 24.0),
+                    Point(x + 3.0, y + 24.0),
+                    Point(x, y + 16.0),
+                )
+            })
+            .collect();
+        parts.push(Box::new(Tinted(arrows.into_iter(), Color::red())));
+
+        let ghost_color = Color::white().with_alpha(0.3);
+        for (ghost, sling) in ghosts {
+            parts.push(Box::new(Tinted(ghost.drawable(), ghost_color)));
+            parts.push(Box::new(Tinted(ghost.active_throttles(), ghost_color)));
+            if let Some(sling) = sling {
+                parts.push(Box::new(Tinted(sling.drawable(), ghost_color)));
+            }
+        }
+        for (ship, sling, state) in ships {
+            parts.push(Box::new(ship.drawable()));
+            if let Some(sling) = sling {
+                parts.push(Box::new(sling.drawable()));
+            }
+            parts.push(Box::new(ship.damage_marks()));
+            if *state == State::Flying {
+                parts.push(Box::new(ship.active_throttles()));
+            }
+        }
+        parts
+    }
+
+    pub fn state(&self) -> State {
+        self.game
+            .pilot(self.local_ship())
+            .map_or(State::GameOver, |pilot| pilot.state())
+    }
+
+    /// Readings of the local ship.
+    pub fn telemetry(&self) -> Option<Telemetry> {
+        let pilot = self.game.pilot(self.local_ship())?;
+        Some(pilot.ship().telemetry())
+    }
+
+    pub fn poll_event(&mut self) -> Option<State> {
+        self.game.poll_ship_event(self.local_ship())
+    }
+
+    pub fn game(&mut self) -> &mut Game {
+        &mut self.game
+    }
+
+    pub fn pause(&mut self) {
+        self.integration.pause();
+    }
+
+    pub fn resume(&mut self) {
+        self.integration.resume();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.integration.paused
+    }
+
+    /// Runs the simulation `scale` times as fast as the clock, up to 16.
+    /// NaN leaves the scale as it was.
+    pub fn set_time_scale(&mut self, scale: f32) {
+        self.integration.set_scale(scale);
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.integration.scale
+    }
+
+    pub fn dropped_time(&self) -> Duration {
+        self.integration.dropped
+    }
+
+    pub fn control(&mut self, throttle: Throttle, level: f32) {
+        match &mut self.network {
+            Some(lockstep) => lockstep.control(throttle, level),
+            None => self.game.control(throttle, level),
+        }
+    }
+
+    pub fn receive(&mut self, message: Message) {
+        if let Some(lockstep) = &mut self.network {
+            lockstep.receive(message);
+        }
     }
 
-    pub fn control(&mut self, throttle: Throttle, activate: bool) {
-        self.ship.throttle(throttle, activate);
+    pub fn poll_outgoing(&mut self) -> Option<Message> {
+        self.network.as_mut()?.poll_outgoing()
+    }
+
+    /// First tick the networked session went out of sync on.
+    pub fn desync(&self) -> Option<u64> {
+        self.network.as_ref()?.desync()
+    }
+
+    pub fn control_ship(&mut self, ship: ShipId, throttle: Throttle, level: f32) {
+        self.game.control_ship(ship, throttle, level);
+    }
+
+    pub fn input_map(&mut self) -> &mut InputMap {
+        &mut self.input
+    }
+
+    /// Feeds a device event through the bindings, steering the local ship
+    /// with whatever is bound to the player.
+    pub fn input(&mut self, event: &Event) {
+        for action in self.input.handle(event) {
+            if action.ship == ShipId::PLAYER {
+                self.control(action.throttle, action.intensity);
+            } else if self.network.is_none() {
+                self.control_ship(action.ship, action.throttle, action.intensity);
+            }
+        }
+    }
+
+    /// Adds a ship, flown with A, W and D unless another ship already is.
+    pub fn add_ship(&mut self) -> ShipId {
+        let ship = self.game.add_ship();
+        self.input.bind_second_player(ship);
+        ship
+    }
+
+    pub fn remove_ship(&mut self, ship: ShipId) -> bool {
+        self.game.remove_ship(ship)
+    }
+
+    pub fn explode(&mut self, center: Point, radius: f32) {
+        self.game.land().deform(center, radius);
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn ticks(integration: &mut IntegrationController, elapsed: Duration) -> (u32, f32) {
+        let mut ticks = 0;
+        let alpha = integration.catch_up(elapsed, || {
+            ticks += 1;
+            true
+        });
+        (ticks, alpha)
+    }
+
+    #[test]
+    fn catch_up_is_capped_and_reported() {
+        let mut integration = IntegrationController::new();
+        let (count, alpha) = ticks(&mut integration, Duration::from_millis(35));
+        assert_eq!(count, 3);
+        assert!((alpha - 0.5).abs() < 0.01);
+        assert_eq!(integration.dropped, Duration::from_secs(0));
+
+        let mut integration = IntegrationController::new();
+        let (count, _) = ticks(&mut integration, Duration::from_millis(1005));
+        assert_eq!(count, integration.max_steps);
+        assert_eq!(integration.dropped, Duration::from_millis(750));
+        assert_eq!(integration.lag, Duration::from_millis(5));
+    }
+
+    #[test]
+    fn stalled_ticks_keep_their_time() {
+        let mut integration = IntegrationController::new();
+        let alpha = integration.catch_up(Duration::from_millis(25), || false);
+        assert_eq!(alpha, 1.0);
+        assert_eq!(integration.lag, Duration::from_millis(25));
+        assert_eq!(integration.dropped, Duration::from_secs(0));
+
+        let (count, alpha) = ticks(&mut integration, Duration::from_millis(0));
+        assert_eq!(count, 2);
+        assert!((alpha - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn paused_time_does_not_tick() {
+        let mut integration = IntegrationController::new();
+        integration.pause();
+        assert_eq!(ticks(&mut integration, Duration::from_secs(5)).0, 0);
+        integration.resume();
+        assert!(!integration.paused);
+        assert_eq!(ticks(&mut integration, Duration::from_millis(20)).0, 2);
+    }
+
+    #[test]
+    fn time_scale_is_kept_sane() {
+        let mut integration = IntegrationController::new();
+        integration.set_scale(2.0);
+        assert_eq!(ticks(&mut integration, Duration::from_millis(50)).0, 10);
+
+        integration.set_scale(f32::NAN);
+        assert_eq!(integration.scale, 2.0);
+        integration.set_scale(f32::INFINITY);
+        assert_eq!(integration.scale, IntegrationController::max_scale());
+        integration.set_scale(-1.0);
+        assert_eq!(integration.scale, 0.0);
+        assert_eq!(ticks(&mut integration, Duration::from_secs(1)).0, 0);
+
+        integration.set_scale(1e30);
+        let (count, _) = ticks(&mut integration, Duration::from_secs(3600));
+        assert_eq!(count, integration.max_steps);
     }
 }